@@ -29,6 +29,40 @@ impl Computer {
     }
   }
 
+  /// Runs like `run`, but reports a bad op code or an out-of-bounds address
+  /// instead of panicking. Patched programs hit both fairly often.
+  pub fn try_run(&mut self) -> Result<(), String> {
+    while !self.halted {
+      self.check_step()?;
+      self.step();
+    }
+    Ok(())
+  }
+
+  fn check_step(&self) -> Result<(), String> {
+    let len = self.memory.len();
+    let op = *self
+      .memory
+      .get(self.pc)
+      .ok_or_else(|| format!("pc {} is out of bounds", self.pc))?;
+    match op {
+      1 | 2 => {
+        if self.pc + 3 >= len {
+          return Err(format!("Truncated instruction at pc {}", self.pc));
+        }
+        for offset in 1..4 {
+          let addr = self.memory[self.pc + offset];
+          if addr >= len {
+            return Err(format!("Address {} out of bounds at pc {}", addr, self.pc));
+          }
+        }
+        Ok(())
+      }
+      99 => Ok(()),
+      op_code => Err(format!("Unknown op code {:?} at pc {}", op_code, self.pc)),
+    }
+  }
+
   fn step(&mut self) {
     if self.halted {
       return;
@@ -112,4 +146,17 @@ mod test {
       vec![30, 1, 1, 4, 2, 5, 6, 0, 99],
     );
   }
+
+  #[test]
+  fn test_try_run() {
+    let mut cpu = Computer::new(vec![1, 0, 0, 0, 99]);
+    assert_eq!(cpu.try_run(), Ok(()));
+    assert_eq!(cpu.memory(), vec![2, 0, 0, 0, 99]);
+
+    let mut cpu = Computer::new(vec![1, 0, 50, 0, 99]);
+    assert!(cpu.try_run().is_err());
+
+    let mut cpu = Computer::new(vec![7, 0, 0, 0, 99]);
+    assert!(cpu.try_run().is_err());
+  }
 }
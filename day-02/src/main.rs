@@ -1,6 +1,8 @@
 mod lib;
+mod search;
 
 use lib::Computer;
+use search::{PatchVar, Search, StopAt};
 
 fn main() {
     part2();
@@ -26,24 +28,17 @@ fn part1() {
 }
 
 const EXPECTED_OUTPUT: usize = 19690720;
+const NUM_WORKERS: usize = 8;
 
 fn part2() {
-    for noun in 0..100 {
-        for verb in 0..100 {
-            let output = test(noun, verb);
-            if output == EXPECTED_OUTPUT {
-                println!("noun: {}, verb: {}", noun, verb);
-                return;
-            }
-        }
+    let vars = vec![PatchVar::new(1, 0..100), PatchVar::new(2, 0..100)];
+    let hits = Search::new(&PROGRAM.to_vec(), &vars)
+        .expect("Noun and verb are inside the program")
+        .workers(NUM_WORKERS)
+        .stop_at(StopAt::FirstHit)
+        .run(|cpu| cpu.output() == EXPECTED_OUTPUT);
+    match hits.first() {
+        Some(hit) => println!("noun: {}, verb: {}", hit.values[0], hit.values[1]),
+        None => println!("no noun/verb produces {}", EXPECTED_OUTPUT),
     }
 }
-
-fn test(noun: usize, verb: usize) -> usize {
-    let mut initial_memory = PROGRAM.to_vec();
-    initial_memory[1] = noun;
-    initial_memory[2] = verb;
-    let mut cpu = Computer::new(initial_memory);
-    cpu.run();
-    cpu.output()
-}
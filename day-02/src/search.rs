@@ -0,0 +1,306 @@
+use crate::lib::{Computer, Memory};
+
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+/// Number of candidates a worker claims at a time. Keeps the shared counter
+/// and the progress channel quiet without hurting early exit much.
+const BATCH_SIZE: usize = 64;
+
+/// A memory address that is overwritten with every value in `values`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchVar {
+    pub addr: usize,
+    pub values: Range<usize>,
+}
+
+impl PatchVar {
+    pub fn new(addr: usize, values: Range<usize>) -> Self {
+        PatchVar { addr, values }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopAt {
+    FirstHit,
+    Exhausted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub checked: usize,
+    pub total: usize,
+}
+
+/// A patch that satisfied the predicate. `index` is the candidate's position
+/// in the cartesian product, with the last variable changing fastest, so
+/// results sort the same way the nested loops in `part2` used to run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hit {
+    pub index: usize,
+    pub values: Vec<usize>,
+    pub memory: Memory,
+}
+
+pub struct Search {
+    base: Memory,
+    vars: Vec<PatchVar>,
+    workers: usize,
+    stop_at: StopAt,
+}
+
+enum Message {
+    Checked(usize),
+    Found(Hit),
+}
+
+impl Search {
+    /// Fails if a variable patches an address outside `base`.
+    pub fn new(base: &Memory, vars: &[PatchVar]) -> Result<Self, String> {
+        if let Some(var) = vars.iter().find(|var| var.addr >= base.len()) {
+            return Err(format!(
+                "Patch address {} is outside memory of length {}",
+                var.addr,
+                base.len()
+            ));
+        }
+
+        Ok(Search {
+            base: base.clone(),
+            vars: vars.to_vec(),
+            workers: 8,
+            stop_at: StopAt::Exhausted,
+        })
+    }
+
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    pub fn stop_at(mut self, stop_at: StopAt) -> Self {
+        self.stop_at = stop_at;
+        self
+    }
+
+    pub fn total(&self) -> usize {
+        self.vars.iter().map(|var| var.values.len()).product()
+    }
+
+    /// Returns the patch values for candidate `index`.
+    pub fn values_at(&self, index: usize) -> Vec<usize> {
+        let mut rest = index;
+        let mut values = vec![0; self.vars.len()];
+        for (i, var) in self.vars.iter().enumerate().rev() {
+            let len = var.values.len();
+            values[i] = var.values.start + rest % len;
+            rest /= len;
+        }
+        values
+    }
+
+    pub fn run<P>(&self, predicate: P) -> Vec<Hit>
+    where
+        P: Fn(&Computer) -> bool + Send + Sync + 'static,
+    {
+        self.run_with_progress(predicate, |_| {})
+    }
+
+    /// Runs every candidate on its own `Computer` across the worker threads.
+    /// Candidates that crash the computer count as misses. Hits come back in
+    /// candidate order no matter which worker found them, and with
+    /// `StopAt::FirstHit` the single hit returned is always the lowest one.
+    pub fn run_with_progress<P, R>(&self, predicate: P, mut report: R) -> Vec<Hit>
+    where
+        P: Fn(&Computer) -> bool + Send + Sync + 'static,
+        R: FnMut(Progress),
+    {
+        let total = self.total();
+        if total == 0 {
+            return vec![];
+        }
+
+        let next = Arc::new(AtomicUsize::new(0));
+        let first_hit = Arc::new(AtomicUsize::new(usize::MAX));
+        let predicate = Arc::new(predicate);
+        let (tx, rx) = mpsc::channel::<Message>();
+
+        let handles = (0..self.workers)
+            .map(|_| {
+                let base = self.base.clone();
+                let vars = self.vars.clone();
+                let stop_at = self.stop_at;
+                let next = next.clone();
+                let first_hit = first_hit.clone();
+                let predicate = predicate.clone();
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    let search = Search {
+                        base,
+                        vars,
+                        workers: 1,
+                        stop_at,
+                    };
+                    loop {
+                        let start = next.fetch_add(BATCH_SIZE, Ordering::SeqCst);
+                        if start >= total || start > first_hit.load(Ordering::SeqCst) {
+                            break;
+                        }
+
+                        let end = total.min(start + BATCH_SIZE);
+                        let mut checked = 0;
+                        for index in start..end {
+                            if stop_at == StopAt::FirstHit
+                                && index > first_hit.load(Ordering::SeqCst)
+                            {
+                                break;
+                            }
+
+                            checked += 1;
+                            if let Some(hit) = search.check(index, predicate.as_ref()) {
+                                if stop_at == StopAt::FirstHit {
+                                    first_hit.fetch_min(index, Ordering::SeqCst);
+                                }
+                                tx.send(Message::Found(hit))
+                                    .expect("Failed to send hit from worker thread");
+                            }
+                        }
+
+                        tx.send(Message::Checked(checked))
+                            .expect("Failed to send progress from worker thread");
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        drop(tx);
+
+        let mut hits = Vec::new();
+        let mut checked = 0;
+        for message in rx {
+            match message {
+                Message::Checked(n) => {
+                    checked += n;
+                    report(Progress { checked, total });
+                }
+                Message::Found(hit) => hits.push(hit),
+            }
+        }
+
+        for handle in handles {
+            handle.join().expect("Search worker panicked");
+        }
+
+        hits.sort_by_key(|hit| hit.index);
+        if self.stop_at == StopAt::FirstHit {
+            hits.truncate(1);
+        }
+        hits
+    }
+
+    fn check<P>(&self, index: usize, predicate: &P) -> Option<Hit>
+    where
+        P: Fn(&Computer) -> bool,
+    {
+        let values = self.values_at(index);
+        let mut memory = self.base.clone();
+        for (var, &value) in self.vars.iter().zip(values.iter()) {
+            memory[var.addr] = value;
+        }
+
+        let mut cpu = Computer::new(memory);
+        match cpu.try_run() {
+            Ok(()) if predicate(&cpu) => Some(Hit {
+                index,
+                values,
+                memory: cpu.memory(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // memory[0] = memory[5] + memory[6], so every patch is a valid program
+    // and the output is easy to predict.
+    fn adder() -> Memory {
+        vec![1, 5, 6, 0, 99, 0, 0]
+    }
+
+    fn noun_verb() -> Vec<PatchVar> {
+        vec![PatchVar::new(5, 0..5), PatchVar::new(6, 0..5)]
+    }
+
+    #[test]
+    fn test_values_at() {
+        let search = Search::new(&adder(), &noun_verb()).unwrap();
+        assert_eq!(search.total(), 25);
+        assert_eq!(search.values_at(0), vec![0, 0]);
+        assert_eq!(search.values_at(1), vec![0, 1]);
+        assert_eq!(search.values_at(5), vec![1, 0]);
+        assert_eq!(search.values_at(24), vec![4, 4]);
+    }
+
+    #[test]
+    fn test_all_hits_are_ordered() {
+        let hits = Search::new(&adder(), &noun_verb())
+            .unwrap()
+            .workers(4)
+            .run(|cpu| cpu.output() == 4);
+        let values = hits
+            .iter()
+            .map(|hit| hit.values.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![vec![0, 4], vec![1, 3], vec![2, 2], vec![3, 1], vec![4, 0]]
+        );
+        assert_eq!(hits[0].memory, vec![4, 5, 6, 0, 99, 0, 4]);
+    }
+
+    #[test]
+    fn test_first_hit_is_lowest() {
+        for workers in 1..6 {
+            let hits = Search::new(&adder(), &noun_verb())
+                .unwrap()
+                .workers(workers)
+                .stop_at(StopAt::FirstHit)
+                .run(|cpu| cpu.output() >= 5);
+            assert_eq!(hits.len(), 1);
+            assert_eq!(hits[0].values, vec![1, 4]);
+        }
+    }
+
+    #[test]
+    fn test_crashes_are_misses() {
+        let vars = vec![PatchVar::new(1, 0..10)];
+        let hits = Search::new(&adder(), &vars).unwrap().run(|_| true);
+        assert_eq!(hits.len(), 7);
+    }
+
+    #[test]
+    fn test_patch_outside_memory() {
+        let vars = vec![PatchVar::new(5, 0..5), PatchVar::new(7, 0..5)];
+        assert_eq!(
+            Search::new(&adder(), &vars).err(),
+            Some("Patch address 7 is outside memory of length 7".to_string())
+        );
+    }
+
+    #[test]
+    fn test_progress() {
+        let mut reports = Vec::new();
+        Search::new(&adder(), &noun_verb())
+            .unwrap()
+            .workers(2)
+            .run_with_progress(|_| false, |progress| reports.push(progress));
+        let last = reports.last().unwrap();
+        assert_eq!(last.checked, 25);
+        assert_eq!(last.total, 25);
+    }
+}
@@ -54,6 +54,10 @@ impl Computer {
     }
   }
 
+  pub fn is_halted(&self) -> bool {
+    self.halted
+  }
+
   pub fn get_memory(&self) -> Memory {
     self.memory.clone()
   }
@@ -72,7 +76,7 @@ impl Computer {
     }
   }
 
-  fn step(&mut self) {
+  pub fn step(&mut self) {
     if self.halted {
       return;
     }
@@ -0,0 +1,2 @@
+pub mod digits;
+pub mod intcode;
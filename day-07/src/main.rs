@@ -1,9 +1,7 @@
 mod combos;
-mod digits;
-mod intcode;
 
 use combos::combos;
-use intcode::{Computer, Memory};
+use day_7::intcode::{Computer, Memory};
 
 fn main() {
     let program = vec![
@@ -1,6 +1,3 @@
-mod digits;
-mod intcode;
-
 use intcode::Computer;
 
 fn main() {
@@ -1,5 +1,4 @@
 mod hull;
-mod intcode;
 mod point;
 mod robot;
 
@@ -33,7 +32,7 @@ fn main() {
 #[cfg(test)]
 mod test {
     use super::hull::{Hull, BLACK, WHITE};
-    use super::intcode::IntcodeComputer;
+    use intcode::IntcodeComputer;
     use super::point::Point;
     use super::robot::{Robot, TURN_LEFT, TURN_RIGHT};
     use std::collections::VecDeque;
@@ -1,5 +1,6 @@
+use intcode::IntcodeComputer;
+
 use crate::hull::Hull;
-use crate::intcode::IntcodeComputer;
 use crate::point::Point;
 
 pub const TURN_LEFT: i64 = 0;
@@ -0,0 +1,240 @@
+use intcode::{parse_fields, Computer, Memory};
+
+use crate::point::Point;
+use crate::screen::{Screen, Tile, SCORE_POINT};
+
+/// Plays breakout without a window, moving the paddle to wherever the ball
+/// is going to come down.
+#[derive(Clone)]
+pub struct Breakout {
+    cpu: Computer,
+    screen: Screen,
+}
+
+impl Breakout {
+    pub fn new(program: &Memory) -> Self {
+        let mut mem = program.clone();
+        mem[0] = 2; // insert two coins
+        Breakout {
+            cpu: Computer::new(&mem),
+            screen: Screen::new(),
+        }
+    }
+
+    pub fn screen(&self) -> &Screen {
+        &self.screen
+    }
+
+    pub fn score(&self) -> i64 {
+        self.screen.score()
+    }
+
+    /// The game is over once the program halts or stops with an error.
+    pub fn is_over(&self) -> bool {
+        self.cpu.is_halted() || self.cpu.get_error().is_some()
+    }
+
+    /// Why the program stopped, if it failed instead of halting.
+    pub fn error(&self) -> Option<String> {
+        self.cpu.get_error().map(|error| error.to_string())
+    }
+
+    /// The score so far, or the error if the program failed.
+    pub fn result(&self) -> Result<i64, String> {
+        match self.error() {
+            Some(error) => Err(error),
+            None => Ok(self.score()),
+        }
+    }
+
+    /// The game's state as `key value` lines, which `load` reads back.
+    pub fn save(&self) -> String {
+        let point = |point: Option<Point>| {
+            point.map_or("none".to_string(), |point| {
+                format!("{},{}", point.x, point.y)
+            })
+        };
+        let mut tiles = self.screen.tiles().collect::<Vec<_>>();
+        tiles.sort_by_key(|&(point, _)| (point.y, point.x));
+        let tiles = tiles
+            .iter()
+            .map(|(point, tile)| format!("{},{},{}", point.x, point.y, tile.to_int()))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        format!(
+            "{}score {}\nball {}\nprevious_ball {}\npaddle {}\ntiles {}\n",
+            self.cpu.save(),
+            self.screen.score(),
+            point(self.screen.ball()),
+            point(self.screen.previous_ball()),
+            point(self.screen.paddle()),
+            tiles
+        )
+    }
+
+    pub fn load(text: &str) -> Result<Self, String> {
+        let fields = parse_fields(text);
+        let numbers = |key: &str| -> Result<Vec<i64>, String> {
+            fields
+                .get(key)
+                .ok_or_else(|| format!("missing {}", key))?
+                .split(|c| [',', ' '].contains(&c))
+                .filter(|n| !n.is_empty() && *n != "none")
+                .map(|n| n.parse().map_err(|_| format!("bad {} {:?}", key, n)))
+                .collect()
+        };
+        let point = |key: &str| -> Result<Option<Point>, String> {
+            match numbers(key)?.as_slice() {
+                [] => Ok(None),
+                [x, y] => Ok(Some(Point::at(*x, *y))),
+                _ => Err(format!("bad {}", key)),
+            }
+        };
+
+        let mut screen = Screen::new();
+        let tiles = numbers("tiles")?;
+        if tiles.len() % 3 != 0 {
+            return Err("bad tiles".to_string());
+        }
+        for tile in tiles.chunks(3) {
+            screen.set(Point::at(tile[0], tile[1]), tile[2]);
+        }
+        let score = fields
+            .get("score")
+            .and_then(|score| score.parse().ok())
+            .ok_or("missing score")?;
+        screen.set(SCORE_POINT, score);
+        if screen.ball() != point("ball")? || screen.paddle() != point("paddle")? {
+            return Err("ball or paddle does not match the tiles".to_string());
+        }
+        screen.set_previous_ball(point("previous_ball")?);
+        screen.begin_frame();
+
+        Ok(Breakout {
+            cpu: Computer::restore(text)?,
+            screen,
+        })
+    }
+
+    /// Runs the game until it wants the joystick again, or ends.
+    pub fn run_frame(&mut self) {
+        self.screen.begin_frame();
+        self.cpu.run();
+
+        while let (Some(x), Some(y), Some(t)) = (
+            self.cpu.get_output(),
+            self.cpu.get_output(),
+            self.cpu.get_output(),
+        ) {
+            self.screen.set(Point::at(x, y), t);
+        }
+    }
+
+    /// Where the ball will come down on the paddle's row, bouncing off the
+    /// side walls on the way. Blocks are ignored, so this is only right once
+    /// the ball is below all of them.
+    fn landing_column(&self) -> Option<i64> {
+        let (ball, previous, paddle) = (
+            self.screen.ball()?,
+            self.screen.previous_ball()?,
+            self.screen.paddle()?,
+        );
+        let mut dx = ball.x - previous.x;
+        if ball.y <= previous.y || dx == 0 {
+            return None;
+        }
+
+        let mut x = ball.x;
+        for y in ball.y..paddle.y - 1 {
+            if self.screen.get(Point::at(x + dx, y)) == Tile::Wall {
+                dx = -dx;
+            }
+            x += dx;
+        }
+        Some(x)
+    }
+
+    fn is_below_blocks(&self, ball: Point) -> bool {
+        self.screen
+            .points(Tile::Block)
+            .all(|block| block.y < ball.y)
+    }
+
+    /// Which way to push the joystick: -1 for left, 1 for right.
+    pub fn joystick(&self) -> i64 {
+        let (ball, paddle) = match (self.screen.ball(), self.screen.paddle()) {
+            (Some(ball), Some(paddle)) => (ball, paddle),
+            _ => return 0,
+        };
+        let target = if self.is_below_blocks(ball) {
+            self.landing_column().unwrap_or(ball.x)
+        } else {
+            ball.x
+        };
+        (target - paddle.x).signum()
+    }
+
+    /// Answers the game's request for the joystick: -1 for left, 0 for
+    /// neutral and 1 for right.
+    pub fn push_joystick(&mut self, direction: i64) {
+        self.cpu.set_input(direction);
+    }
+
+    /// Runs one frame and answers it with the autopilot's joystick.
+    pub fn step(&mut self) {
+        self.run_frame();
+        if !self.is_over() {
+            self.push_joystick(self.joystick());
+        }
+    }
+
+    /// Plays until the game ends and returns the final score.
+    pub fn play(&mut self) -> Result<i64, String> {
+        while !self.is_over() {
+            self.step();
+        }
+        self.result()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intcode::load_program;
+
+    #[test]
+    fn test_autopilot_clears_the_board() {
+        let mut game = Breakout::new(&load_program("./game.intcode"));
+        let score = game.play();
+        assert_eq!(game.screen().num_blocks(), 0);
+        assert_eq!(score, Ok(19210));
+    }
+
+    #[test]
+    fn test_failed_program_ends_the_game() {
+        // The first cell becomes 2 for the coins, then op code 42 fails.
+        let mut game = Breakout::new(&vec![1, 0, 0, 0, 42]);
+        assert_eq!(game.play(), Err("unknown op code 42 at pc 4".to_string()));
+        assert!(game.is_over());
+    }
+
+    #[test]
+    fn test_save_load() {
+        let mut game = Breakout::new(&load_program("./game.intcode"));
+        for _ in 0..100 {
+            game.step();
+        }
+        let mut loaded = Breakout::load(&game.save()).unwrap();
+        assert_eq!(loaded.save(), game.save());
+
+        for _ in 0..100 {
+            game.step();
+            loaded.step();
+        }
+        assert_eq!(loaded.score(), game.score());
+        assert_eq!(loaded.save(), game.save());
+
+        assert!(Breakout::load("score 12").is_err());
+    }
+}
@@ -1,47 +1,72 @@
-use std::collections::{HashMap, VecDeque};
+use std::path::Path;
 
 use ggez::event::{quit, EventHandler};
-use ggez::graphics::{self, Color, DrawMode, DrawParam, Drawable, Mesh, MeshBuilder, Rect};
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Drawable, MeshBuilder, Rect, Text};
 use ggez::input::keyboard::{KeyCode, KeyMods};
 use ggez::{Context, GameResult};
+use intcode::Memory;
 
-use crate::intcode::Computer;
+use crate::breakout::Breakout;
 use crate::point::Point;
+use crate::recorder::Recorder;
+use crate::saves::{load_slot, save_slot, RewindBuffer};
+use crate::screen::Tile;
 
 const BLOCK_SIZE: f32 = 10.0;
 
+/// Room under the board for the score line.
+pub const HUD_HEIGHT: f32 = 30.0;
+
+/// Ten seconds of play at normal speed.
+const REWIND_FRAMES: usize = 600;
+
+/// Game frames per window frame.
+const SPEEDS: [f32; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+const NORMAL_SPEED: usize = 2;
+
+const SAVE_DIR: &str = "./saves";
+
+/// Controls:
+///
+/// - Left and Right move the paddle
+/// - Space pauses
+/// - `[` and `]` slow the game down and speed it up
+/// - Backspace rewinds while held
+/// - 1 to 3 pick a save slot, F5 saves to it and F9 loads from it
+/// - Escape quits
 pub struct Game {
-    cpu: Computer,
-    score: i64,
-    output: VecDeque<i64>,
-    walls: Vec<Mesh>,
-    blocks: HashMap<Point, Mesh>,
-    ball: Option<Mesh>,
-    paddle: Option<Mesh>,
+    breakout: Breakout,
+    rewind: RewindBuffer,
+    joystick: i64,
+    paused: bool,
+    rewinding: bool,
+    speed: usize,
+    /// Game frames owed to slow speeds.
+    progress: f32,
+    slot: usize,
+    message: Option<String>,
+    /// Every frame shown, rewinds included.
+    recorder: Recorder,
 }
 
 impl Game {
-    pub fn new(cpu: Computer, _ctx: &mut Context) -> Self {
+    pub fn new(program: &Memory, _ctx: &mut Context) -> Self {
         Game {
-            cpu,
-            score: 0,
-            output: VecDeque::new(),
-            walls: Vec::new(),
-            blocks: HashMap::new(),
-            ball: None,
-            paddle: None,
+            breakout: Breakout::new(program),
+            rewind: RewindBuffer::new(REWIND_FRAMES),
+            joystick: 0,
+            paused: false,
+            rewinding: false,
+            speed: NORMAL_SPEED,
+            progress: 0.0,
+            slot: 1,
+            message: None,
+            recorder: Recorder::new(),
         }
     }
 
-    fn process_instruction(&mut self) -> (i64, i64, i64) {
-        let x = self.output.pop_front().expect("Failed to get output");
-        let y = self.output.pop_front().expect("Failed to get output");
-        let t = self.output.pop_front().expect("Failed to get output");
-        (x, y, t)
-    }
-
-    fn remove_block(&mut self, point: Point) {
-        self.blocks.remove(&point);
+    pub fn recording(self) -> Recorder {
+        self.recorder
     }
 
     fn rect_at(&self, point: Point) -> Rect {
@@ -53,89 +78,88 @@ impl Game {
         )
     }
 
-    fn add_wall(&mut self, point: Point, ctx: &mut Context) {
-        let rect = MeshBuilder::new()
-            .rectangle(
-                DrawMode::fill(),
-                self.rect_at(point),
-                Color::from_rgb(0xe5, 0x31, 0x70),
-            )
-            .build(ctx)
-            .expect("Failed to build wall");
-        self.walls.push(rect);
+    fn color(tile: Tile) -> Option<Color> {
+        match tile {
+            Tile::Empty => None,
+            tile => {
+                let [r, g, b] = tile.rgb();
+                Some(Color::from_rgb(r, g, b))
+            }
+        }
+    }
+
+    fn run_frame(&mut self) {
+        self.rewind.push(&self.breakout);
+        self.breakout.run_frame();
+        self.recorder.capture(self.breakout.screen());
+        if !self.breakout.is_over() {
+            self.breakout.push_joystick(self.joystick);
+        }
     }
 
-    fn add_block(&mut self, point: Point, ctx: &mut Context) {
-        let rect = MeshBuilder::new()
-            .rectangle(
-                DrawMode::fill(),
-                self.rect_at(point),
-                Color::from_rgb(0xf2, 0x5f, 0x4c),
-            )
-            .build(ctx)
-            .expect("Failed to build block");
-        self.blocks.insert(point, rect);
+    fn quick_save(&mut self) {
+        let result = save_slot(Path::new(SAVE_DIR), self.slot, &self.breakout);
+        self.message = Some(match result {
+            Ok(()) => format!("saved slot {}", self.slot),
+            Err(err) => err,
+        });
     }
 
-    fn update_paddle(&mut self, point: Point, ctx: &mut Context) {
-        let rect = MeshBuilder::new()
-            .rectangle(
-                DrawMode::fill(),
-                self.rect_at(point),
-                Color::from_rgb(0xff, 0x89, 0x06),
-            )
-            .build(ctx)
-            .expect("Failed to build paddle");
-        self.paddle = Some(rect);
+    fn quick_load(&mut self) {
+        self.message = Some(match load_slot(Path::new(SAVE_DIR), self.slot) {
+            Ok(breakout) => {
+                self.breakout = breakout;
+                self.rewind = RewindBuffer::new(REWIND_FRAMES);
+                format!("loaded slot {}", self.slot)
+            }
+            Err(err) => err,
+        });
     }
 
-    fn update_ball(&mut self, point: Point, ctx: &mut Context) {
-        let rect = MeshBuilder::new()
-            .rectangle(
-                DrawMode::fill(),
-                self.rect_at(point),
-                Color::from_rgb(0xff, 0xff, 0xfe),
-            )
-            .build(ctx)
-            .expect("Failed to build ball");
-        self.ball = Some(rect);
+    fn status(&self) -> String {
+        let screen = self.breakout.screen();
+        let mut status = format!(
+            "score {}  blocks {}  {}x  slot {}",
+            self.breakout.score(),
+            screen.num_blocks(),
+            SPEEDS[self.speed],
+            self.slot
+        );
+        if self.rewinding {
+            status.push_str("  REWIND");
+        } else if self.paused {
+            status.push_str("  PAUSED");
+        } else if let Some(error) = self.breakout.error() {
+            status.push_str("  FAILED: ");
+            status.push_str(&error);
+        } else if self.breakout.is_over() {
+            status.push_str("  GAME OVER");
+        }
+        if let Some(message) = &self.message {
+            status.push_str("  ");
+            status.push_str(message);
+        }
+        status
     }
 }
 
 impl EventHandler for Game {
-    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
-        self.cpu.set_input(0);
-
-        for _ in 0..10000 {
-            self.cpu.step();
-            if self.cpu.is_blocked() {
-                break;
+    fn update(&mut self, _ctx: &mut Context) -> GameResult<()> {
+        if self.rewinding {
+            if let Some(snapshot) = self.rewind.pop() {
+                self.breakout = snapshot;
+                self.recorder.capture(self.breakout.screen());
             }
+            return Ok(());
         }
-
-        loop {
-            match self.cpu.get_output() {
-                Some(output) => self.output.push_back(output),
-                None => break,
-            };
+        if self.paused || self.breakout.is_over() {
+            return Ok(());
         }
 
-        while self.output.len() >= 3 {
-            let (x, y, t) = self.process_instruction();
-            if x == -1 && y == 0 {
-                self.score = t;
-                println!("score: {}", self.score);
-            } else {
-                let point = Point::at(x, y);
-                match t {
-                    0 => self.remove_block(point),
-                    1 => self.add_wall(point, ctx),
-                    2 => self.add_block(point, ctx),
-                    3 => self.update_paddle(point, ctx),
-                    4 => self.update_ball(point, ctx),
-                    _ => (),
-                };
-            }
+        self.progress += SPEEDS[self.speed];
+        while self.progress >= 1.0 && !self.breakout.is_over() {
+            self.progress -= 1.0;
+            self.run_frame();
         }
 
         Ok(())
@@ -146,44 +170,60 @@ impl EventHandler for Game {
         ctx: &mut Context,
         keycode: KeyCode,
         _keymods: KeyMods,
-        _repeat: bool,
+        repeat: bool,
     ) {
         match keycode {
-            KeyCode::Left => self.cpu.set_input(-1),
-            KeyCode::Right => self.cpu.set_input(1),
+            KeyCode::Left => self.joystick = -1,
+            KeyCode::Right => self.joystick = 1,
+            KeyCode::Back => self.rewinding = true,
             KeyCode::Escape => quit(ctx),
+            // Everything else is a toggle, so holding it down should not
+            // repeat it.
+            _ if repeat => (),
+            KeyCode::Space => self.paused = !self.paused,
+            KeyCode::LBracket => self.speed = self.speed.saturating_sub(1),
+            KeyCode::RBracket => self.speed = (self.speed + 1).min(SPEEDS.len() - 1),
+            KeyCode::Key1 => self.slot = 1,
+            KeyCode::Key2 => self.slot = 2,
+            KeyCode::Key3 => self.slot = 3,
+            KeyCode::F5 => self.quick_save(),
+            KeyCode::F9 => self.quick_load(),
             _ => (),
         };
     }
 
-    fn key_up_event(&mut self, _ctx: &mut Context, _keycode: KeyCode, _keymods: KeyMods) {
-        self.cpu.set_input(0);
+    fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode, _keymods: KeyMods) {
+        match keycode {
+            KeyCode::Left | KeyCode::Right => self.joystick = 0,
+            KeyCode::Back => self.rewinding = false,
+            _ => (),
+        };
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
         graphics::clear(ctx, graphics::BLACK);
 
-        for wall in self.walls.iter() {
-            wall.draw(ctx, DrawParam::default())
-                .expect("Failed to draw wall");
-        }
-
-        for block in self.blocks.values() {
-            block
-                .draw(ctx, DrawParam::default())
-                .expect("Failed to draw block");
+        let screen = self.breakout.screen();
+        let mut board = MeshBuilder::new();
+        let mut tiles = 0;
+        for (point, tile) in screen.tiles() {
+            if let Some(color) = Self::color(tile) {
+                board.rectangle(DrawMode::fill(), self.rect_at(point), color);
+                tiles += 1;
+            }
         }
-
-        if let Some(paddle) = self.paddle.as_ref() {
-            paddle
+        // ggez will not build a mesh with nothing in it.
+        if tiles > 0 {
+            board
+                .build(ctx)?
                 .draw(ctx, DrawParam::default())
-                .expect("Failed to draw paddle");
+                .expect("Failed to draw board");
         }
 
-        if let Some(ball) = self.ball.as_ref() {
-            ball.draw(ctx, DrawParam::default())
-                .expect("Failed to draw ball");
-        }
+        let hud_top = BLOCK_SIZE * screen.height() as f32 + 8.0;
+        Text::new(self.status())
+            .draw(ctx, DrawParam::default().dest([4.0, hud_top]))
+            .expect("Failed to draw HUD");
 
         graphics::present(ctx)
     }
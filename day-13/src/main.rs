@@ -1,20 +1,57 @@
+mod breakout;
 mod game;
-mod intcode;
 mod point;
+mod recorder;
+mod saves;
 mod screen;
+mod terminal;
 
 use ggez::event;
 use ggez::graphics::{self, Rect};
 use ggez::ContextBuilder;
+use intcode::{load_program, AsyncComputer, IntcodeComputer};
 
-use game::Game;
-use intcode::{load_program, AsyncComputer, Computer, IntcodeComputer};
+use std::env;
+use std::path::PathBuf;
+
+use breakout::Breakout;
+use game::{Game, HUD_HEIGHT};
 use point::Point;
+use recorder::Recorder;
 use screen::Screen;
 
 fn main() {
-    part1();
-    part2();
+    // `play` opens a window to play the game by hand, and `terminal` plays
+    // it in the terminal instead, by hand or with `terminal auto`. Any run
+    // can be recorded with `--record run.gif`, or with `--record frames` for
+    // a directory of PPM images.
+    let mut args = env::args().collect::<Vec<_>>();
+    let record = args.iter().position(|arg| arg == "--record").map(|i| {
+        let path = PathBuf::from(args.get(i + 1).expect("--record needs a path"));
+        args.drain(i..=i + 1);
+        path
+    });
+
+    let mut recorder = Recorder::new();
+    match args.get(1).map(String::as_str) {
+        Some("play") => recorder = play(),
+        Some("terminal") => {
+            let autopilot = args.get(2).map(String::as_str) == Some("auto");
+            match terminal::play(&load_program("./game.intcode"), autopilot, &mut recorder) {
+                Ok(score) => println!("score: {}", score),
+                Err(error) => eprintln!("the game failed: {}", error),
+            }
+        }
+        _ => {
+            part1();
+            part2(record.as_ref().map(|_| &mut recorder));
+        }
+    }
+
+    if let Some(path) = record {
+        recorder.export(&path).expect("Failed to export recording");
+        println!("recorded {} frames to {}", recorder.len(), path.display());
+    }
 }
 
 fn part1() {
@@ -45,22 +82,40 @@ fn recv_instruction<T: IntcodeComputer>(cpu: &mut T) -> Option<(i64, i64, i64)>
     }
 }
 
-fn part2() {
+fn part2(recorder: Option<&mut Recorder>) {
+    let mut game = Breakout::new(&load_program("./game.intcode"));
+    let result = match recorder {
+        Some(recorder) => {
+            while !game.is_over() {
+                game.step();
+                recorder.capture(game.screen());
+            }
+            game.result()
+        }
+        None => game.play(),
+    };
+    match result {
+        Ok(score) => println!("part 2: {}", score),
+        Err(error) => eprintln!("part 2 failed: {}", error),
+    }
+}
+
+fn play() -> Recorder {
     let (mut ctx, mut event_loop) = ContextBuilder::new("day-13", "Advent of Code")
         .build()
         .expect("Failed to create ggez context");
 
-    graphics::set_drawable_size(&mut ctx, 370.0, 260.0).expect("Failed to set drawable size");
-    graphics::set_screen_coordinates(&mut ctx, Rect::new(0.0, 0.0, 370.0, 260.0))
+    let height = 260.0 + HUD_HEIGHT;
+    graphics::set_drawable_size(&mut ctx, 370.0, height).expect("Failed to set drawable size");
+    graphics::set_screen_coordinates(&mut ctx, Rect::new(0.0, 0.0, 370.0, height))
         .expect("Failed to set screen coords");
 
-    let mut mem = load_program("./hacked_game.intcode");
-    mem[0] = 2; // insert two coins
-    let cpu = Computer::new(&mem);
-    let mut game = Game::new(cpu, &mut ctx);
+    let mut game = Game::new(&load_program("./game.intcode"), &mut ctx);
 
     match event::run(&mut ctx, &mut event_loop, &mut game) {
         Ok(_) => (),
         Err(_) => (),
     };
+
+    game.recording()
 }
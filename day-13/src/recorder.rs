@@ -0,0 +1,439 @@
+//! Records the screen frame by frame so a run can be replayed elsewhere,
+//! either as an animated GIF or as a numbered PPM sequence for tools like
+//! ffmpeg. Both use the same colours as the game window.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::point::Point;
+use crate::screen::{Screen, Tile};
+
+/// Pixels per tile along each side.
+pub const SCALE: usize = 4;
+
+/// How long each game frame is shown in the GIF, in hundredths of a second.
+const GIF_DELAY: usize = 3;
+
+/// Colour table bits. Tiles are looked up by `Tile::to_int`, with every
+/// unknown tile sharing the entry after the ball's.
+const PALETTE_BITS: u8 = 3;
+const UNKNOWN_INDEX: u8 = 5;
+
+/// LZW codes never grow past twelve bits.
+const MAX_CODES: u16 = 4096;
+
+#[derive(PartialEq, Eq)]
+struct Frame {
+    width: usize,
+    height: usize,
+    tiles: Vec<u8>,
+}
+
+impl Frame {
+    fn capture(screen: &Screen) -> Self {
+        let (width, height) = (screen.width() as usize, screen.height() as usize);
+        let mut tiles = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                tiles.push(palette_index(screen.get(Point::at(x as i64, y as i64))));
+            }
+        }
+        Frame {
+            width,
+            height,
+            tiles,
+        }
+    }
+
+    /// The tile at a cell, empty outside the frame. The screen grows while
+    /// the game first draws it, so early frames can be smaller than later
+    /// ones.
+    fn get(&self, x: usize, y: usize) -> u8 {
+        if x < self.width && y < self.height {
+            self.tiles[y * self.width + x]
+        } else {
+            0
+        }
+    }
+}
+
+/// A frame and how many game frames in a row looked like it.
+struct Shot {
+    frame: Frame,
+    length: usize,
+}
+
+#[derive(Default)]
+pub struct Recorder {
+    shots: Vec<Shot>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Recorder { shots: Vec::new() }
+    }
+
+    /// The number of frames captured.
+    pub fn len(&self) -> usize {
+        self.shots.iter().map(|shot| shot.length).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shots.is_empty()
+    }
+
+    /// Adds the screen as the next frame. A frame that looks like the last
+    /// one only makes the last one last longer.
+    pub fn capture(&mut self, screen: &Screen) {
+        let frame = Frame::capture(screen);
+        match self.shots.last_mut() {
+            Some(shot) if shot.frame == frame => shot.length += 1,
+            _ => self.shots.push(Shot { frame, length: 1 }),
+        }
+    }
+
+    /// Writes a GIF when `path` ends in `.gif`, and a PPM sequence into the
+    /// directory `path` otherwise.
+    pub fn export(&self, path: &Path) -> io::Result<()> {
+        if path.extension().is_some_and(|ext| ext == "gif") {
+            fs::write(path, self.to_gif())
+        } else {
+            self.write_ppm_sequence(path)
+        }
+    }
+
+    /// Size of the largest frame, in tiles.
+    fn size(&self) -> (usize, usize) {
+        self.shots.iter().fold((0, 0), |(width, height), shot| {
+            (width.max(shot.frame.width), height.max(shot.frame.height))
+        })
+    }
+
+    /// Writes `frame-00001.ppm` and onwards into `dir`, one file for every
+    /// captured frame.
+    pub fn write_ppm_sequence(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let (width, height) = self.size();
+        let mut number = 0;
+        for shot in &self.shots {
+            let ppm = to_ppm(&shot.frame, width, height);
+            for _ in 0..shot.length {
+                number += 1;
+                fs::write(dir.join(format!("frame-{:05}.ppm", number)), &ppm)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// An animated GIF that loops forever. After the first frame, each frame
+    /// only covers the tiles that changed.
+    pub fn to_gif(&self) -> Vec<u8> {
+        let (width, height) = self.size();
+        let mut gif = Vec::new();
+
+        gif.extend_from_slice(b"GIF89a");
+        push_u16(&mut gif, width * SCALE);
+        push_u16(&mut gif, height * SCALE);
+        let bits = PALETTE_BITS - 1;
+        gif.extend_from_slice(&[0x80 | bits << 4 | bits, 0, 0]);
+        for i in 0..1 << PALETTE_BITS {
+            gif.extend_from_slice(&color(i));
+        }
+
+        // Loop forever.
+        gif.extend_from_slice(&[0x21, 0xff, 0x0b]);
+        gif.extend_from_slice(b"NETSCAPE2.0");
+        gif.extend_from_slice(&[0x03, 0x01, 0x00, 0x00, 0x00]);
+
+        let blank = Frame {
+            width: 0,
+            height: 0,
+            tiles: Vec::new(),
+        };
+        let mut previous = &blank;
+        for (i, shot) in self.shots.iter().enumerate() {
+            let changed = if i == 0 {
+                Some((0, 0, width, height))
+            } else {
+                changed_area(previous, &shot.frame, width, height)
+            };
+            // Captures never repeat, but the tiles a frame leaves off the
+            // screen can make it look like the last one anyway.
+            let (left, top, right, bottom) = changed.unwrap_or((0, 0, 1, 1));
+
+            // Keep what is already drawn, and show it for as long as the
+            // frame lasted.
+            gif.extend_from_slice(&[0x21, 0xf9, 0x04, 0x04]);
+            push_u16(&mut gif, (shot.length * GIF_DELAY).min(0xffff));
+            gif.extend_from_slice(&[0x00, 0x00]);
+
+            gif.push(0x2c);
+            push_u16(&mut gif, left * SCALE);
+            push_u16(&mut gif, top * SCALE);
+            push_u16(&mut gif, (right - left) * SCALE);
+            push_u16(&mut gif, (bottom - top) * SCALE);
+            gif.push(0x00);
+
+            let mut pixels = Vec::new();
+            for y in top * SCALE..bottom * SCALE {
+                for x in left * SCALE..right * SCALE {
+                    pixels.push(shot.frame.get(x / SCALE, y / SCALE));
+                }
+            }
+            gif.push(PALETTE_BITS);
+            for block in lzw_encode(&pixels, PALETTE_BITS).chunks(255) {
+                gif.push(block.len() as u8);
+                gif.extend_from_slice(block);
+            }
+            gif.push(0x00);
+
+            previous = &shot.frame;
+        }
+
+        gif.push(0x3b);
+        gif
+    }
+}
+
+fn palette_index(tile: Tile) -> u8 {
+    match tile {
+        Tile::Unknown(_) => UNKNOWN_INDEX,
+        tile => tile.to_int() as u8,
+    }
+}
+
+/// Past the known tiles, every entry in the table is an unknown tile.
+fn color(index: u8) -> [u8; 3] {
+    Tile::from_int(i64::from(index)).rgb()
+}
+
+fn push_u16(out: &mut Vec<u8>, n: usize) {
+    out.extend_from_slice(&(n as u16).to_le_bytes());
+}
+
+/// A binary PPM of the frame, padded to `width` by `height` tiles.
+fn to_ppm(frame: &Frame, width: usize, height: usize) -> Vec<u8> {
+    let mut ppm = format!("P6\n{} {}\n255\n", width * SCALE, height * SCALE).into_bytes();
+    for y in 0..height * SCALE {
+        for x in 0..width * SCALE {
+            ppm.extend_from_slice(&color(frame.get(x / SCALE, y / SCALE)));
+        }
+    }
+    ppm
+}
+
+/// The smallest box of tiles, as left, top, right and bottom with the right
+/// and bottom exclusive, that holds every difference between two frames.
+fn changed_area(
+    before: &Frame,
+    after: &Frame,
+    width: usize,
+    height: usize,
+) -> Option<(usize, usize, usize, usize)> {
+    let mut area: Option<(usize, usize, usize, usize)> = None;
+    for y in 0..height {
+        for x in 0..width {
+            if before.get(x, y) != after.get(x, y) {
+                area = Some(match area {
+                    None => (x, y, x + 1, y + 1),
+                    Some((left, top, right, bottom)) => {
+                        (left.min(x), top.min(y), right.max(x + 1), bottom.max(y + 1))
+                    }
+                });
+            }
+        }
+    }
+    area
+}
+
+/// Packs variable width codes least significant bit first, as GIF wants.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, width: u8) {
+        self.buffer |= u32::from(code) << self.bits;
+        self.bits += width;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+/// Compresses colour indices with GIF's flavour of LZW, starting over with a
+/// clear code whenever the table fills up.
+fn lzw_encode(pixels: &[u8], min_width: u8) -> Vec<u8> {
+    let clear = 1u16 << min_width;
+    let end = clear + 1;
+    let mut out = BitWriter {
+        bytes: Vec::new(),
+        buffer: 0,
+        bits: 0,
+    };
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next = end + 1;
+    let mut width = min_width + 1;
+    out.write(clear, width);
+
+    let mut pixels = pixels.iter();
+    let mut prefix = match pixels.next() {
+        Some(&pixel) => u16::from(pixel),
+        None => {
+            out.write(end, width);
+            return out.finish();
+        }
+    };
+    for &pixel in pixels {
+        if let Some(&code) = table.get(&(prefix, pixel)) {
+            prefix = code;
+            continue;
+        }
+
+        out.write(prefix, width);
+        if next < MAX_CODES {
+            table.insert((prefix, pixel), next);
+            // The reader adds each code one step behind, so it only needs
+            // the wider codes once this one is past the current width.
+            if next == 1 << width {
+                width += 1;
+            }
+            next += 1;
+        } else {
+            out.write(clear, width);
+            table.clear();
+            next = end + 1;
+            width = min_width + 1;
+        }
+        prefix = u16::from(pixel);
+    }
+    out.write(prefix, width);
+    out.write(end, width);
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A straightforward GIF LZW reader to check the encoder against.
+    fn lzw_decode(bytes: &[u8], min_width: u8) -> Vec<u8> {
+        let clear = 1usize << min_width;
+        let end = clear + 1;
+        let mut table: Vec<Vec<u8>> = Vec::new();
+        let mut width = min_width + 1;
+        let mut previous: Option<Vec<u8>> = None;
+        let mut pixels = Vec::new();
+
+        let (mut buffer, mut bits, mut bytes) = (0u32, 0u8, bytes.iter());
+        loop {
+            while bits < width {
+                buffer |= u32::from(*bytes.next().unwrap()) << bits;
+                bits += 8;
+            }
+            let code = (buffer & ((1 << width) - 1)) as usize;
+            buffer >>= width;
+            bits -= width;
+
+            if code == clear {
+                table = (0..clear).map(|i| vec![i as u8]).collect();
+                table.push(Vec::new());
+                table.push(Vec::new());
+                width = min_width + 1;
+                previous = None;
+                continue;
+            }
+            if code == end {
+                return pixels;
+            }
+
+            let entry = match (table.get(code), &previous) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(previous)) => {
+                    let mut entry = previous.clone();
+                    entry.push(previous[0]);
+                    entry
+                }
+                (None, None) => panic!("Code {} before anything was read", code),
+            };
+            if let Some(mut previous) = previous {
+                if table.len() < MAX_CODES as usize {
+                    previous.push(entry[0]);
+                    table.push(previous);
+                    if table.len() == 1 << width && width < 12 {
+                        width += 1;
+                    }
+                }
+            }
+            pixels.extend_from_slice(&entry);
+            previous = Some(entry);
+        }
+    }
+
+    #[test]
+    fn test_lzw_round_trip() {
+        let runs = (0..5000).map(|i| (i / 7 % 5) as u8).collect::<Vec<_>>();
+        // Enough variety to fill the table and force a clear code.
+        let noise = (0..20000u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 29) as u8)
+            .collect::<Vec<_>>();
+        for pixels in &[vec![], vec![3], runs, noise] {
+            let encoded = lzw_encode(pixels, PALETTE_BITS);
+            assert_eq!(&lzw_decode(&encoded, PALETTE_BITS), pixels);
+        }
+    }
+
+    fn screen(tiles: &[(i64, i64, i64)]) -> Screen {
+        let mut screen = Screen::new();
+        for &(x, y, t) in tiles {
+            screen.set(Point::at(x, y), t);
+        }
+        screen
+    }
+
+    #[test]
+    fn test_capture() {
+        let mut recorder = Recorder::new();
+        let mut screen = screen(&[(0, 0, 1), (1, 1, 4)]);
+        recorder.capture(&screen);
+        recorder.capture(&screen);
+        screen.set(Point::at(1, 1), 0);
+        screen.set(Point::at(2, 1), 4);
+        recorder.capture(&screen);
+
+        assert_eq!(recorder.len(), 3);
+        assert_eq!(recorder.shots.len(), 2);
+        assert_eq!(recorder.size(), (3, 2));
+        assert_eq!(
+            changed_area(&recorder.shots[0].frame, &recorder.shots[1].frame, 3, 2),
+            Some((1, 1, 3, 2))
+        );
+
+        let ppm = to_ppm(&recorder.shots[0].frame, 3, 2);
+        let header = format!("P6\n{} {}\n255\n", 3 * SCALE, 2 * SCALE);
+        assert!(ppm.starts_with(header.as_bytes()));
+        assert_eq!(ppm.len(), header.len() + 3 * SCALE * 2 * SCALE * 3);
+        assert_eq!(ppm[header.len()..header.len() + 3], Tile::Wall.rgb());
+
+        let gif = recorder.to_gif();
+        assert!(gif.starts_with(b"GIF89a"));
+        assert_eq!(gif[6..10], [12, 0, 8, 0]);
+        assert_eq!(gif.last(), Some(&0x3b));
+
+        let unknown = Tile::Unknown(9);
+        assert_eq!(color(palette_index(unknown)), unknown.rgb());
+    }
+}
@@ -0,0 +1,102 @@
+//! Snapshots of a breakout game: a rewind buffer kept in memory and
+//! numbered save slots kept on disk.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::breakout::Breakout;
+
+/// Keeps the last `limit` snapshots, dropping the oldest. A limit of zero
+/// still keeps the most recent one.
+pub struct RewindBuffer {
+    snapshots: VecDeque<Breakout>,
+    limit: usize,
+}
+
+impl RewindBuffer {
+    pub fn new(limit: usize) -> Self {
+        RewindBuffer {
+            snapshots: VecDeque::new(),
+            limit,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn push(&mut self, game: &Breakout) {
+        while self.snapshots.len() >= self.limit.max(1) {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(game.clone());
+    }
+
+    /// The most recent snapshot, which is dropped from the buffer.
+    pub fn pop(&mut self) -> Option<Breakout> {
+        self.snapshots.pop_back()
+    }
+}
+
+fn slot_path(dir: &Path, slot: usize) -> PathBuf {
+    dir.join(format!("slot-{}.save", slot))
+}
+
+pub fn save_slot(dir: &Path, slot: usize, game: &Breakout) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    fs::write(slot_path(dir, slot), game.save()).map_err(|err| err.to_string())
+}
+
+pub fn load_slot(dir: &Path, slot: usize) -> Result<Breakout, String> {
+    let text =
+        fs::read_to_string(slot_path(dir, slot)).map_err(|_| format!("slot {} is empty", slot))?;
+    Breakout::load(&text).map_err(|err| format!("slot {} is broken: {}", slot, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intcode::load_program;
+
+    fn after(steps: usize) -> Breakout {
+        let mut game = Breakout::new(&load_program("./game.intcode"));
+        for _ in 0..steps {
+            game.step();
+        }
+        game
+    }
+
+    #[test]
+    fn test_rewind_buffer() {
+        let mut buffer = RewindBuffer::new(3);
+        for steps in 0..5 {
+            buffer.push(&after(steps));
+        }
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.pop().unwrap().save(), after(4).save());
+        assert_eq!(buffer.pop().unwrap().save(), after(3).save());
+        assert_eq!(buffer.pop().unwrap().save(), after(2).save());
+        assert!(buffer.pop().is_none());
+
+        let mut buffer = RewindBuffer::new(0);
+        buffer.push(&after(0));
+        buffer.push(&after(1));
+        assert_eq!(buffer.len(), 1);
+        assert_eq!(buffer.pop().unwrap().save(), after(1).save());
+    }
+
+    #[test]
+    fn test_slots() {
+        let dir = std::env::temp_dir().join(format!("day-13-saves-{}", std::process::id()));
+        let game = after(10);
+        save_slot(&dir, 1, &game).unwrap();
+        assert_eq!(load_slot(&dir, 1).unwrap().save(), game.save());
+        assert_eq!(
+            load_slot(&dir, 2).err(),
+            Some("slot 2 is empty".to_string())
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
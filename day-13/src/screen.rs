@@ -2,13 +2,19 @@ use std::collections::HashMap;
 
 use crate::point::Point;
 
-#[derive(Debug, Copy, Clone)]
+/// The arcade cabinet shows the score here instead of drawing a tile.
+pub const SCORE_POINT: Point = Point { x: -1, y: 0 };
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Tile {
     Empty,
     Wall,
     Block,
     Paddle,
     Ball,
+    /// A tile id the cabinet is not supposed to send, kept so it can be
+    /// shown rather than crashing the game.
+    Unknown(i64),
 }
 
 impl Tile {
@@ -19,7 +25,30 @@ impl Tile {
             2 => Tile::Block,
             3 => Tile::Paddle,
             4 => Tile::Ball,
-            bad_n => panic!("Could not create Tile from integer {}", bad_n),
+            n => Tile::Unknown(n),
+        }
+    }
+
+    pub fn to_int(self) -> i64 {
+        match self {
+            Self::Empty => 0,
+            Self::Wall => 1,
+            Self::Block => 2,
+            Self::Paddle => 3,
+            Self::Ball => 4,
+            Self::Unknown(n) => n,
+        }
+    }
+
+    /// The colour the game draws the tile in, with the background black.
+    pub fn rgb(self) -> [u8; 3] {
+        match self {
+            Self::Empty => [0x00, 0x00, 0x00],
+            Self::Wall => [0xe5, 0x31, 0x70],
+            Self::Block => [0xf2, 0x5f, 0x4c],
+            Self::Paddle => [0xff, 0x89, 0x06],
+            Self::Ball => [0xff, 0xff, 0xfe],
+            Self::Unknown(_) => [0x80, 0x80, 0x80],
         }
     }
 
@@ -30,14 +59,32 @@ impl Tile {
             Self::Block => 'B',  //'\u{1F7E7}',  // orange
             Self::Paddle => 'P', //'\u{1F7E8}', // yellow
             Self::Ball => 'O',   //'\u{26AA}',    // white circle
+            Self::Unknown(_) => '?',
         }
     }
 }
 
+/// Something the game drew during the current frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Change {
+    Tile(Point, Tile),
+    Score(i64),
+}
+
+/// Everything the game has drawn, with the score and the positions of the
+/// ball and paddle kept up to date as it draws.
+#[derive(Clone)]
 pub struct Screen {
     tiles: HashMap<Point, Tile>,
     x_max: i64,
     y_max: i64,
+    score: i64,
+    ball: Option<Point>,
+    /// Where the ball was before it last moved.
+    previous_ball: Option<Point>,
+    paddle: Option<Point>,
+    blocks: usize,
+    changes: Vec<Change>,
 }
 
 impl Screen {
@@ -46,24 +93,112 @@ impl Screen {
             tiles: HashMap::new(),
             x_max: 0,
             y_max: 0,
+            score: 0,
+            ball: None,
+            previous_ball: None,
+            paddle: None,
+            blocks: 0,
+            changes: Vec::new(),
         }
     }
 
+    /// Applies one of the game's `(x, y, value)` outputs.
     pub fn set(&mut self, point: Point, n: i64) {
+        if point == SCORE_POINT {
+            self.score = n;
+            self.changes.push(Change::Score(n));
+            return;
+        }
+
         let tile = Tile::from_int(n);
         self.x_max = self.x_max.max(point.x);
         self.y_max = self.y_max.max(point.y);
-        self.tiles.insert(point, tile);
+        let before = self.tiles.insert(point, tile).unwrap_or(Tile::Empty);
+        self.changes.push(Change::Tile(point, tile));
+
+        if before == Tile::Block {
+            self.blocks -= 1;
+        }
+        if before == Tile::Ball && self.ball == Some(point) {
+            self.previous_ball = self.ball.take();
+        }
+        if before == Tile::Paddle && self.paddle == Some(point) {
+            self.paddle = None;
+        }
+
+        match tile {
+            Tile::Block => self.blocks += 1,
+            Tile::Ball => {
+                // The game may draw the ball's new spot before it blanks the
+                // old one.
+                if self.ball.is_some() {
+                    self.previous_ball = self.ball;
+                }
+                self.ball = Some(point);
+            }
+            Tile::Paddle => self.paddle = Some(point),
+            _ => (),
+        }
     }
 
-    pub fn num_blocks(&self) -> usize {
+    /// Starts a new frame, forgetting the changes made in the last one.
+    pub fn begin_frame(&mut self) {
+        self.changes.clear();
+    }
+
+    /// What was drawn since `begin_frame`, in order.
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    pub fn score(&self) -> i64 {
+        self.score
+    }
+
+    pub fn ball(&self) -> Option<Point> {
+        self.ball
+    }
+
+    pub fn previous_ball(&self) -> Option<Point> {
+        self.previous_ball
+    }
+
+    /// Puts back the ball's last position when restoring a saved game, since
+    /// redrawing the tiles only places the ball.
+    pub fn set_previous_ball(&mut self, point: Option<Point>) {
+        self.previous_ball = point;
+    }
+
+    pub fn paddle(&self) -> Option<Point> {
+        self.paddle
+    }
+
+    /// Every tile the game has drawn, including the ones it blanked out.
+    pub fn tiles(&self) -> impl Iterator<Item = (Point, Tile)> + '_ {
+        self.tiles.iter().map(|(&point, &tile)| (point, tile))
+    }
+
+    pub fn width(&self) -> i64 {
+        self.x_max + 1
+    }
+
+    pub fn height(&self) -> i64 {
+        self.y_max + 1
+    }
+
+    pub fn get(&self, point: Point) -> Tile {
+        *self.tiles.get(&point).unwrap_or(&Tile::Empty)
+    }
+
+    pub fn points(&self, tile: Tile) -> impl Iterator<Item = Point> + '_ {
         self.tiles
             .iter()
-            .filter(|(_, &t)| match t {
-                Tile::Block => true,
-                _ => false,
-            })
-            .count()
+            .filter(move |(_, &t)| t == tile)
+            .map(|(&point, _)| point)
+    }
+
+    pub fn num_blocks(&self) -> usize {
+        self.blocks
     }
 
     pub fn to_string(&self) -> String {
@@ -80,3 +215,55 @@ impl Screen {
         output
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracks_game_state() {
+        let mut screen = Screen::new();
+        screen.set(Point::at(0, 0), 2);
+        screen.set(Point::at(1, 0), 2);
+        screen.set(Point::at(2, 3), 3);
+        screen.set(Point::at(1, 2), 4);
+        screen.set(SCORE_POINT, 0);
+        assert_eq!(screen.num_blocks(), 2);
+        assert_eq!(screen.paddle(), Some(Point::at(2, 3)));
+        assert_eq!(screen.ball(), Some(Point::at(1, 2)));
+
+        screen.begin_frame();
+        screen.set(Point::at(1, 0), 0);
+        screen.set(SCORE_POINT, 12);
+        screen.set(Point::at(1, 2), 0);
+        screen.set(Point::at(2, 1), 4);
+        assert_eq!(screen.num_blocks(), 1);
+        assert_eq!(screen.score(), 12);
+        assert_eq!(screen.ball(), Some(Point::at(2, 1)));
+        assert_eq!(screen.previous_ball(), Some(Point::at(1, 2)));
+        assert_eq!(
+            screen.changes(),
+            &[
+                Change::Tile(Point::at(1, 0), Tile::Empty),
+                Change::Score(12),
+                Change::Tile(Point::at(1, 2), Tile::Empty),
+                Change::Tile(Point::at(2, 1), Tile::Ball),
+            ]
+        );
+
+        // Drawing the new ball first gives the same answer.
+        screen.set(Point::at(3, 2), 4);
+        screen.set(Point::at(2, 1), 0);
+        assert_eq!(screen.ball(), Some(Point::at(3, 2)));
+        assert_eq!(screen.previous_ball(), Some(Point::at(2, 1)));
+    }
+
+    #[test]
+    fn test_unknown_tiles() {
+        let mut screen = Screen::new();
+        screen.set(Point::at(1, 0), 9);
+        assert_eq!(screen.get(Point::at(1, 0)), Tile::Unknown(9));
+        assert_eq!(screen.get(Point::at(1, 0)).to_int(), 9);
+        assert_eq!(screen.to_string(), " ?\n");
+    }
+}
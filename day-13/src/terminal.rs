@@ -0,0 +1,250 @@
+//! Plays breakout in a terminal with ANSI escape codes, for when there is
+//! no window to open. Only the cells that changed since the last frame are
+//! redrawn.
+
+use std::collections::HashMap;
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use intcode::Memory;
+
+use crate::breakout::Breakout;
+use crate::point::Point;
+use crate::recorder::Recorder;
+use crate::screen::{Screen, Tile};
+
+const FRAME_TIME: Duration = Duration::from_millis(60);
+
+const HIDE_CURSOR: &str = "\x1b[?25l";
+const SHOW_CURSOR: &str = "\x1b[?25h";
+const CLEAR_SCREEN: &str = "\x1b[2J";
+const CLEAR_LINE: &str = "\x1b[2K";
+
+/// Moves the cursor to a cell. The terminal counts rows and columns from 1.
+fn move_to(out: &mut String, row: i64, column: i64) {
+    write!(out, "\x1b[{};{}H", row + 1, column + 1).unwrap();
+}
+
+pub struct TerminalRenderer {
+    drawn: HashMap<Point, Tile>,
+    frames: usize,
+}
+
+impl TerminalRenderer {
+    pub fn new() -> Self {
+        TerminalRenderer {
+            drawn: HashMap::new(),
+            frames: 0,
+        }
+    }
+
+    /// Escape codes that bring the terminal up to date with `screen`,
+    /// followed by the status line.
+    pub fn render(&mut self, screen: &Screen) -> String {
+        self.frames += 1;
+
+        let mut changed = screen
+            .tiles()
+            .filter(|&(point, tile)| self.drawn.get(&point).copied().unwrap_or(Tile::Empty) != tile)
+            .collect::<Vec<_>>();
+        changed.sort_by_key(|&(point, _)| (point.y, point.x));
+
+        let mut out = String::new();
+        for (point, tile) in changed {
+            move_to(&mut out, point.y, point.x);
+            out.push(tile.to_char());
+            self.drawn.insert(point, tile);
+        }
+
+        move_to(&mut out, screen.height() + 1, 0);
+        write!(
+            out,
+            "{}score: {}  blocks: {}  frame: {}",
+            CLEAR_LINE,
+            screen.score(),
+            screen.num_blocks(),
+            self.frames
+        )
+        .unwrap();
+        out
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Key {
+    Left,
+    Right,
+    Quit,
+}
+
+/// Turns raw terminal input into keys, including the arrow keys' escape
+/// sequences. Anything else is dropped.
+#[derive(Default)]
+pub struct KeyParser {
+    pending: Vec<u8>,
+}
+
+impl KeyParser {
+    pub fn feed(&mut self, byte: u8) -> Option<Key> {
+        if !self.pending.is_empty() || byte == 0x1b {
+            self.pending.push(byte);
+            return match self.pending.as_slice() {
+                [0x1b] | [0x1b, b'['] => None,
+                [0x1b, b'[', code] => {
+                    let key = match code {
+                        b'D' => Some(Key::Left),
+                        b'C' => Some(Key::Right),
+                        _ => None,
+                    };
+                    self.pending.clear();
+                    key
+                }
+                _ => {
+                    self.pending.clear();
+                    None
+                }
+            };
+        }
+
+        match byte {
+            b'a' | b'h' => Some(Key::Left),
+            b'd' | b'l' => Some(Key::Right),
+            // Ctrl-C does not interrupt in raw mode.
+            b'q' | 3 => Some(Key::Quit),
+            _ => None,
+        }
+    }
+}
+
+/// Puts the terminal in raw mode until dropped, so keys arrive as soon as
+/// they are pressed and are not echoed.
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> Self {
+        stty(&["raw", "-echo"]);
+        RawMode
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        stty(&["-raw", "echo"]);
+    }
+}
+
+fn stty(args: &[&str]) {
+    // Not being attached to a terminal is fine, the keys just never come.
+    Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .status()
+        .ok();
+}
+
+fn read_keys() -> Receiver<Key> {
+    let (key_tx, key_rx) = channel();
+    thread::spawn(move || {
+        let mut parser = KeyParser::default();
+        for byte in io::stdin().lock().bytes() {
+            let key = match byte {
+                Ok(byte) => parser.feed(byte),
+                Err(_) => break,
+            };
+            if let Some(key) = key {
+                if key_tx.send(key).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+    key_rx
+}
+
+/// Plays the game in the terminal, steered by the arrow keys or by the
+/// autopilot, capturing every frame. Returns the score when the game ends or
+/// the player quits, or the error if the program failed.
+pub fn play(program: &Memory, autopilot: bool, recorder: &mut Recorder) -> Result<i64, String> {
+    let mut game = Breakout::new(program);
+    let mut renderer = TerminalRenderer::new();
+    let raw_mode = RawMode::enable();
+    let keys = read_keys();
+    let mut stdout = io::stdout();
+    write!(stdout, "{}{}", HIDE_CURSOR, CLEAR_SCREEN).unwrap();
+
+    loop {
+        game.run_frame();
+        recorder.capture(game.screen());
+        write!(stdout, "{}", renderer.render(game.screen())).unwrap();
+        stdout.flush().unwrap();
+        if game.is_over() {
+            break;
+        }
+
+        let mut direction = 0;
+        let mut quit = false;
+        for key in keys.try_iter() {
+            match key {
+                Key::Left => direction = -1,
+                Key::Right => direction = 1,
+                Key::Quit => quit = true,
+            }
+        }
+        if quit {
+            break;
+        }
+        game.push_joystick(if autopilot {
+            game.joystick()
+        } else {
+            direction
+        });
+        thread::sleep(FRAME_TIME);
+    }
+
+    drop(raw_mode);
+    writeln!(stdout, "{}", SHOW_CURSOR).unwrap();
+    game.result()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen::SCORE_POINT;
+
+    #[test]
+    fn test_render_changed_cells() {
+        let mut screen = Screen::new();
+        screen.set(Point::at(0, 0), 1);
+        screen.set(Point::at(1, 0), 2);
+        screen.set(Point::at(1, 1), 4);
+
+        let mut renderer = TerminalRenderer::new();
+        let first = renderer.render(&screen);
+        assert!(first.starts_with("\x1b[1;1HW\x1b[1;2HB\x1b[2;2HO"));
+        assert!(first.ends_with("score: 0  blocks: 1  frame: 1"));
+
+        screen.set(Point::at(1, 0), 0);
+        screen.set(SCORE_POINT, 12);
+        let second = renderer.render(&screen);
+        assert_eq!(
+            second,
+            "\x1b[1;2H \x1b[4;1H\x1b[2Kscore: 12  blocks: 0  frame: 2"
+        );
+    }
+
+    #[test]
+    fn test_key_parser() {
+        let mut parser = KeyParser::default();
+        let keys = b"a\x1b[Cx\x1b[Ad\x1bq"
+            .iter()
+            .filter_map(|&byte| parser.feed(byte))
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec![Key::Left, Key::Right, Key::Right]);
+        assert_eq!(parser.feed(b'q'), Some(Key::Quit));
+    }
+}
@@ -1,19 +1,36 @@
 use crate::droid::{Direction, Droid};
 use crate::point::Point;
 use crate::section_map::SectionMap;
+use crate::strategy::{DepthFirst, Strategy};
 
 use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Stats {
+    pub moves: usize,
+    pub wall_bumps: usize,
+    /// Moves into cells the droid had already been in.
+    pub revisits: usize,
+}
+
 pub struct Ai {
     pos: Point,
     frontier: VecDeque<Point>,
     explored: HashSet<Point>,
+    visited: HashSet<Point>,
     plan: VecDeque<Direction>,
     oxygen_system: Option<Point>,
+    strategy: Box<dyn Strategy>,
+    stopped: bool,
+    stats: Stats,
 }
 
 impl Ai {
     pub fn new(map: &SectionMap) -> Self {
+        Ai::with_strategy(map, Box::new(DepthFirst))
+    }
+
+    pub fn with_strategy(map: &SectionMap, strategy: Box<dyn Strategy>) -> Self {
         let pos = Point::at(0, 0);
         let mut explored = HashSet::new();
         explored.insert(pos);
@@ -23,12 +40,24 @@ impl Ai {
         Ai {
             pos,
             frontier,
+            visited: explored.clone(),
             explored,
             plan: VecDeque::new(),
             oxygen_system: None,
+            strategy,
+            stopped: false,
+            stats: Stats::default(),
         }
     }
 
+    pub fn get_strategy_name(&self) -> String {
+        self.strategy.name()
+    }
+
+    pub fn get_stats(&self) -> Stats {
+        self.stats
+    }
+
     pub fn get_oxygen_system(&self) -> Point {
         self.oxygen_system.expect("Failed to get oxygen system")
     }
@@ -38,7 +67,15 @@ impl Ai {
     }
 
     pub fn done_exploring(&self) -> bool {
-        self.frontier.len() == 0
+        self.explored_everything() || self.stopped
+    }
+
+    /// Whether there is nowhere left to explore, as opposed to the strategy
+    /// stopping early.
+    pub fn explored_everything(&self) -> bool {
+        // The last goal leaves the frontier as soon as it is picked, before
+        // the droid gets there.
+        self.frontier.is_empty() && self.plan.is_empty()
     }
 
     pub fn get_path_length(&self, map: &SectionMap) -> usize {
@@ -64,6 +101,14 @@ impl Ai {
 
         let attempted_pos = self.pos + try_dir.to_vector();
         self.explored.insert(attempted_pos);
+        self.frontier.retain(|&point| point != attempted_pos);
+
+        self.stats.moves += 1;
+        if result.is_wall() {
+            self.stats.wall_bumps += 1;
+        } else if !self.visited.insert(attempted_pos) {
+            self.stats.revisits += 1;
+        }
 
         if !result.is_wall() {
             self.pos = attempted_pos;
@@ -87,18 +132,14 @@ impl Ai {
     }
 
     fn get_next_dir(&mut self, map: &SectionMap) -> Option<Direction> {
-        if self.plan.len() == 0 && self.frontier.len() > 0 {
-            let mut next_goal = self
-                .frontier
-                .pop_front()
-                .expect("Failed to get point from frontier");
-            while self.explored.contains(&next_goal) && self.frontier.len() > 0 {
-                next_goal = self
-                    .frontier
-                    .pop_front()
-                    .expect("Failed to get point from frontier");
+        if self.plan.is_empty() && !self.done_exploring() {
+            match self.strategy.next_goal(self.pos, &self.frontier, map) {
+                Some(next_goal) => {
+                    self.frontier.retain(|&point| point != next_goal);
+                    self.plan = plan_path(self.pos, next_goal, map);
+                }
+                None => self.stopped = true,
             }
-            self.plan = plan_path(self.pos, next_goal, map);
         }
 
         if self.plan.len() > 0 {
@@ -137,7 +178,8 @@ impl PartialOrd for Node {
     }
 }
 
-fn plan_path(start: Point, goal: Point, map: &SectionMap) -> VecDeque<Direction> {
+/// The moves from `start` to `goal` through known open cells.
+pub fn plan_path(start: Point, goal: Point, map: &SectionMap) -> VecDeque<Direction> {
     let mut frontier = BinaryHeap::new();
     let mut total_costs = HashMap::new();
     let mut came_from = HashMap::new();
@@ -154,7 +196,7 @@ fn plan_path(start: Point, goal: Point, map: &SectionMap) -> VecDeque<Direction>
         let neighbors = map.get_neighbors(current.point);
         let valid_neighbors = neighbors
             .iter()
-            .filter(|&neighbor| map.is_empty(neighbor) || *neighbor == goal);
+            .filter(|&neighbor| map.is_open(neighbor) || *neighbor == goal);
         for &neighbor in valid_neighbors {
             let current_cost = total_costs
                 .get(&current.point)
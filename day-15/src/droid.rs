@@ -1,7 +1,8 @@
-use crate::intcode::Computer;
+use intcode::Computer;
+
 use crate::point::Point;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum Direction {
     North,
     South,
@@ -10,6 +11,10 @@ pub enum Direction {
 }
 
 impl Direction {
+    pub fn all() -> [Direction; 4] {
+        [Self::North, Self::South, Self::East, Self::West]
+    }
+
     pub fn from_string(string: &str) -> Option<Direction> {
         match string.trim().to_uppercase().as_ref() {
             "N" => Some(Direction::North),
@@ -91,16 +96,25 @@ impl MoveResult {
     }
 }
 
+#[derive(Clone)]
 pub struct Droid {
     cpu: Computer,
+    moves: usize,
 }
 
 impl Droid {
     pub fn new(cpu: Computer) -> Self {
-        Droid { cpu }
+        Droid { cpu, moves: 0 }
+    }
+
+    /// How many moves the droid has been told to make, including the ones
+    /// that ran into walls.
+    pub fn get_moves(&self) -> usize {
+        self.moves
     }
 
     pub fn try_move(&mut self, direction: Direction) -> MoveResult {
+        self.moves += 1;
         self.cpu.set_input(direction.to_data());
         self.cpu.run();
         let output = self
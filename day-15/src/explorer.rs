@@ -0,0 +1,88 @@
+//! Explores the whole section breadth-first without walking the droid
+//! around. Every open cell gets its own copy of the droid, so each cell is
+//! only ever stepped into once, and the first time the oxygen system turns
+//! up is along a shortest path.
+
+use crate::droid::{Direction, Droid};
+use crate::point::Point;
+use crate::section_map::SectionMap;
+
+use std::collections::{HashSet, VecDeque};
+
+pub struct Exploration {
+    pub map: SectionMap,
+    pub oxygen_system: Option<Point>,
+    /// Moves from the start to the oxygen system.
+    pub distance: Option<usize>,
+    /// Moves made across all the copies of the droid.
+    pub moves: usize,
+}
+
+pub fn explore(droid: &Droid) -> Exploration {
+    let start = Point::at(0, 0);
+    let mut map = SectionMap::new();
+    let mut oxygen_system = None;
+    let mut distance = None;
+    let mut moves = 0;
+
+    let mut visited = HashSet::new();
+    visited.insert(start);
+    let mut frontier = VecDeque::new();
+    frontier.push_back((start, droid.clone(), 0));
+
+    while let Some((pos, droid, steps)) = frontier.pop_front() {
+        for &dir in Direction::all().iter() {
+            let next = pos + dir.to_vector();
+            if visited.contains(&next) {
+                continue;
+            }
+            visited.insert(next);
+
+            let mut fork = droid.clone();
+            let result = fork.try_move(dir);
+            moves += 1;
+            map.set_result(next, result);
+            if result.is_wall() {
+                continue;
+            }
+            if result.is_oxygen_system() && oxygen_system.is_none() {
+                oxygen_system = Some(next);
+                distance = Some(steps + 1);
+            }
+            frontier.push_back((next, fork, steps + 1));
+        }
+    }
+
+    Exploration {
+        map,
+        oxygen_system,
+        distance,
+        moves,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::Ai;
+    use intcode::{load_program, Computer};
+
+    #[test]
+    fn test_matches_ai() {
+        let droid = Droid::new(Computer::new(&load_program("./repair.intcode")));
+        let exploration = explore(&droid);
+
+        let mut ai_droid = droid.clone();
+        let mut ai_map = SectionMap::new();
+        let mut ai = Ai::new(&ai_map);
+        while !ai.done_exploring() {
+            ai.update(&mut ai_droid, &mut ai_map);
+        }
+
+        let origin = Point::at(0, 0);
+        assert_eq!(exploration.map.to_string(origin), ai_map.to_string(origin));
+        assert_eq!(exploration.oxygen_system, Some(ai.get_oxygen_system()));
+        assert_eq!(exploration.distance, Some(ai.get_path_length(&ai_map)));
+        assert!(exploration.moves < ai_droid.get_moves());
+    }
+}
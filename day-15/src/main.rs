@@ -1,21 +1,49 @@
 mod ai;
 mod droid;
-mod intcode;
+mod explorer;
+mod oxygen;
 mod point;
 mod section_map;
+mod strategy;
 
-use ai::Ai;
-use droid::{Direction, Droid, MoveResult};
+use ai::{plan_path, Ai};
+use droid::{Direction, Droid};
 use intcode::{load_program, Computer};
+use oxygen::Flood;
 use point::Point;
 use section_map::SectionMap;
 
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
 // use std::io;
 
 fn main() {
     // demo();
-    part1();
-    part2();
+
+    // `explore` maps the section breadth-first with copies of the droid and
+    // solves both parts from that, and `bench` compares it with the droid
+    // walking around by itself. `save FILE` writes the explored map out, and
+    // `load FILE` solves both parts from a saved map without the droid.
+    // `strategies` compares the ways the droid can choose where to explore.
+    // `heatmap FILE DIR` writes the oxygen spreading over a saved map out as
+    // PPM images.
+    let args = env::args().collect::<Vec<_>>();
+    let file = || args.get(2).expect("Expected a map file");
+    match args.get(1).map(String::as_str) {
+        Some("explore") => explore(),
+        Some("bench") => bench(),
+        Some("strategies") => compare_strategies(),
+        Some("save") => save(file()),
+        Some("load") => load(file()),
+        Some("heatmap") => heatmap(file(), args.get(3).expect("Expected a directory")),
+        _ => {
+            part1();
+            part2();
+        }
+    }
 }
 
 // #[allow(dead_code)]
@@ -74,33 +102,120 @@ fn part2() {
     }
 
     let oxygen_system = ai.get_oxygen_system();
-    let minutes = flood_oxygen(oxygen_system, &mut map);
+    let minutes = flood_oxygen(oxygen_system, &map);
     println!("part 2: {}", minutes);
 }
 
-fn flood_oxygen(oxygen_system: Point, map: &mut SectionMap) -> usize {
-    let mut minutes = 0;
+fn explore() {
+    let mem = load_program("./repair.intcode");
+    let droid = Droid::new(Computer::new(&mem));
+    let exploration = explorer::explore(&droid);
 
-    let mut frontier = Vec::new();
-    let neighbors = map.get_empty_neighbors(oxygen_system);
-    frontier.extend(neighbors.iter());
+    println!("{}", exploration.map.to_string(Point::at(0, 0)));
+    println!("part 1: {:?}", exploration.distance);
 
-    loop {
-        let mut next_frontier = Vec::new();
-        while frontier.len() > 0 {
-            let current = frontier.pop().expect("Failed to get point from frontier");
-            map.set_result(current, MoveResult::Oxygen);
-            let new_frontier = map.get_empty_neighbors(current);
-            next_frontier.extend(new_frontier.iter());
-        }
-        frontier = next_frontier;
-        println!("{}", map.to_string(Point::at(0, 0)));
-        minutes += 1;
+    let oxygen_system = exploration
+        .oxygen_system
+        .expect("Failed to find oxygen system");
+    let minutes = flood_oxygen(oxygen_system, &exploration.map);
+    println!("part 2: {}", minutes);
+}
 
-        if frontier.len() == 0 {
-            break;
+fn save(filename: &str) {
+    let droid = Droid::new(Computer::new(&load_program("./repair.intcode")));
+    let exploration = explorer::explore(&droid);
+    fs::write(filename, exploration.map.save()).expect("Failed to write map");
+}
+
+fn load(filename: &str) {
+    let text = fs::read_to_string(filename).expect("Failed to read map");
+    let map = SectionMap::load(&text).expect("Failed to load map");
+    let oxygen_system = map
+        .get_oxygen_system()
+        .expect("Expected oxygen system on the map");
+
+    let length = plan_path(oxygen_system, Point::at(0, 0), &map).len();
+    println!("part 1: {}", length);
+    let minutes = flood_oxygen(oxygen_system, &map);
+    println!("part 2: {}", minutes);
+}
+
+fn heatmap(filename: &str, dir: &str) {
+    let text = fs::read_to_string(filename).expect("Failed to read map");
+    let map = SectionMap::load(&text).expect("Failed to load map");
+    let oxygen_system = map
+        .get_oxygen_system()
+        .expect("Expected oxygen system on the map");
+
+    let flood = Flood::new(&map, oxygen_system);
+    flood
+        .write_ppm_sequence(&map, Path::new(dir))
+        .expect("Failed to write heatmap");
+    println!("spread per minute: {:?}", flood.get_spread());
+    println!("last to fill: {:?}", flood.get_farthest());
+}
+
+fn compare_strategies() {
+    let mem = load_program("./repair.intcode");
+
+    println!(
+        "{:<24} {:>6} {:>6} {:>9} {:>9}",
+        "strategy", "moves", "bumps", "revisits", "explored"
+    );
+    for strategy in strategy::all_strategies() {
+        let mut droid = Droid::new(Computer::new(&mem));
+        let mut map = SectionMap::new();
+        let mut ai = Ai::with_strategy(&map, strategy);
+        while !ai.done_exploring() {
+            ai.update(&mut droid, &mut map);
         }
+
+        let stats = ai.get_stats();
+        let explored = if ai.explored_everything() {
+            "all"
+        } else {
+            "partly"
+        };
+        println!(
+            "{:<24} {:>6} {:>6} {:>9} {:>9}",
+            ai.get_strategy_name(),
+            stats.moves,
+            stats.wall_bumps,
+            stats.revisits,
+            explored
+        );
     }
+}
+
+fn bench() {
+    let mem = load_program("./repair.intcode");
+
+    let start = Instant::now();
+    let mut droid = Droid::new(Computer::new(&mem));
+    let mut map = SectionMap::new();
+    let mut ai = Ai::new(&map);
+    while !ai.done_exploring() {
+        ai.update(&mut droid, &mut map);
+    }
+    println!(
+        "ai:       {:>5} moves, distance {}, {:?}",
+        droid.get_moves(),
+        ai.get_path_length(&map),
+        start.elapsed()
+    );
+
+    let start = Instant::now();
+    let exploration = explorer::explore(&Droid::new(Computer::new(&mem)));
+    println!(
+        "explorer: {:>5} moves, distance {}, {:?}",
+        exploration.moves,
+        exploration.distance.expect("Failed to find oxygen system"),
+        start.elapsed()
+    );
+}
 
-    minutes
+fn flood_oxygen(oxygen_system: Point, map: &SectionMap) -> usize {
+    let flood = Flood::new(map, oxygen_system);
+    flood.print(map);
+    flood.get_minutes()
 }
@@ -0,0 +1,207 @@
+//! Oxygen spreading out from the repaired oxygen system, one open cell
+//! further every minute.
+
+use crate::droid::MoveResult;
+use crate::point::Point;
+use crate::section_map::SectionMap;
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Pixels per cell along each side in PPM frames.
+const SCALE: i64 = 6;
+
+const WALL: [u8; 3] = [0x50, 0x50, 0x50];
+const OPEN: [u8; 3] = [0x18, 0x18, 0x28];
+const UNKNOWN: [u8; 3] = [0x00, 0x00, 0x00];
+/// Oxygen fades from the first colour where it starts to the second where
+/// it arrives last.
+const HEAT: [[u8; 3]; 2] = [[0xff, 0xe8, 0x40], [0xa0, 0x00, 0x30]];
+
+pub struct Flood {
+    arrivals: HashMap<Point, usize>,
+    minutes: usize,
+}
+
+impl Flood {
+    /// Fills every open cell reachable from `source`, breadth-first.
+    pub fn new(map: &SectionMap, source: Point) -> Self {
+        let mut arrivals = HashMap::new();
+        arrivals.insert(source, 0);
+        let mut minutes = 0;
+
+        let mut frontier = VecDeque::new();
+        frontier.push_back(source);
+        while let Some(current) = frontier.pop_front() {
+            let arrival = arrivals[&current] + 1;
+            for next in map.get_empty_neighbors(current) {
+                if let Entry::Vacant(entry) = arrivals.entry(next) {
+                    entry.insert(arrival);
+                    minutes = arrival;
+                    frontier.push_back(next);
+                }
+            }
+        }
+
+        Flood { arrivals, minutes }
+    }
+
+    /// Minutes until every reachable cell has oxygen.
+    pub fn get_minutes(&self) -> usize {
+        self.minutes
+    }
+
+    /// The minute oxygen reaches a cell, if it ever does.
+    pub fn get_arrival(&self, point: Point) -> Option<usize> {
+        self.arrivals.get(&point).cloned()
+    }
+
+    /// The cells that fill last, from the north-west.
+    pub fn get_farthest(&self) -> Vec<Point> {
+        let mut farthest = self
+            .arrivals
+            .iter()
+            .filter(|(_, &arrival)| arrival == self.minutes)
+            .map(|(&point, _)| point)
+            .collect::<Vec<_>>();
+        farthest.sort_by_key(|point| (-point.y, point.x));
+        farthest
+    }
+
+    /// How many cells fill in each minute, starting with the source itself
+    /// at minute 0.
+    pub fn get_spread(&self) -> Vec<usize> {
+        let mut spread = vec![0; self.minutes + 1];
+        for &arrival in self.arrivals.values() {
+            spread[arrival] += 1;
+        }
+        spread
+    }
+
+    fn get_heat(&self, arrival: usize) -> [u8; 3] {
+        let t = arrival as f64 / self.minutes.max(1) as f64;
+        let mut color = [0; 3];
+        for i in 0..3 {
+            let (from, to) = (HEAT[0][i] as f64, HEAT[1][i] as f64);
+            color[i] = (from + (to - from) * t).round() as u8;
+        }
+        color
+    }
+
+    /// The map after `minute` minutes, with `O` where there is oxygen. With
+    /// `ansi` set, the oxygen is coloured by when it arrived.
+    pub fn frame(&self, map: &SectionMap, minute: usize, ansi: bool) -> String {
+        let (min, max) = map.get_bounds();
+        let mut output = String::new();
+
+        for y in (min.y..=max.y).rev() {
+            for x in min.x..=max.x {
+                let point = Point::at(x, y);
+                match (self.get_arrival(point), map.get_result(&point)) {
+                    (Some(arrival), _) if arrival <= minute => {
+                        if ansi {
+                            let [r, g, b] = self.get_heat(arrival);
+                            output.push_str(&format!("\x1b[38;2;{};{};{}mO\x1b[0m", r, g, b));
+                        } else {
+                            output.push('O');
+                        }
+                    }
+                    (_, Some(MoveResult::Wall)) => output.push('#'),
+                    (_, Some(_)) => output.push('.'),
+                    (_, None) => output.push(' '),
+                }
+            }
+            output.push('\n');
+        }
+
+        output
+    }
+
+    /// A binary PPM of the map after `minute` minutes.
+    pub fn to_ppm(&self, map: &SectionMap, minute: usize) -> Vec<u8> {
+        let (min, max) = map.get_bounds();
+        let (width, height) = (max.x - min.x + 1, max.y - min.y + 1);
+        let mut ppm = format!("P6\n{} {}\n255\n", width * SCALE, height * SCALE).into_bytes();
+
+        for row in 0..height * SCALE {
+            for column in 0..width * SCALE {
+                let point = Point::at(min.x + column / SCALE, max.y - row / SCALE);
+                let color = match (self.get_arrival(point), map.get_result(&point)) {
+                    (Some(arrival), _) if arrival <= minute => self.get_heat(arrival),
+                    (_, Some(MoveResult::Wall)) => WALL,
+                    (_, Some(_)) => OPEN,
+                    (_, None) => UNKNOWN,
+                };
+                ppm.extend_from_slice(&color);
+            }
+        }
+
+        ppm
+    }
+
+    /// Writes `minute-000.ppm` and onwards into `dir`, from the start to the
+    /// minute the last cell fills.
+    pub fn write_ppm_sequence(&self, map: &SectionMap, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        for minute in 0..=self.minutes {
+            let path = dir.join(format!("minute-{:03}.ppm", minute));
+            fs::write(path, self.to_ppm(map, minute))?;
+        }
+        Ok(())
+    }
+
+    /// Prints every minute's frame in colour.
+    pub fn print(&self, map: &SectionMap) {
+        for minute in 0..=self.minutes {
+            println!("{}", self.frame(map, minute, true));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The example from the puzzle, which fills in four minutes.
+    const EXAMPLE: &str = "min -1,-3\nmax 4,1\noxygen 1,-2\n\n \
+        ##   \n#S.## \n#.#..#\n#.O.# \n ###  \n";
+
+    #[test]
+    fn test_flood() {
+        let map = SectionMap::load(EXAMPLE).unwrap();
+        let flood = Flood::new(&map, map.get_oxygen_system().unwrap());
+
+        assert_eq!(flood.get_minutes(), 4);
+        assert_eq!(flood.get_spread(), vec![1, 2, 2, 2, 1]);
+        assert_eq!(flood.get_farthest(), vec![Point::at(1, 0)]);
+        assert_eq!(flood.get_arrival(Point::at(3, -1)), Some(3));
+        assert_eq!(flood.get_arrival(Point::at(-1, -3)), None);
+
+        assert_eq!(
+            flood.frame(&map, 2, false),
+            " ##   \n#..## \n#O#O.#\n#OOO# \n ###  \n"
+        );
+    }
+
+    #[test]
+    fn test_to_ppm() {
+        let map = SectionMap::load(EXAMPLE).unwrap();
+        let flood = Flood::new(&map, map.get_oxygen_system().unwrap());
+        let ppm = flood.to_ppm(&map, 0);
+
+        let header = format!("P6\n{} {}\n255\n", 6 * SCALE, 5 * SCALE);
+        assert!(ppm.starts_with(header.as_bytes()));
+        assert_eq!(
+            ppm.len(),
+            header.len() + (6 * 5 * SCALE * SCALE * 3) as usize
+        );
+
+        // The source is at column 2, row 3 from the top.
+        let pixel = header.len() + ((3 * SCALE * 6 * SCALE + 2 * SCALE) * 3) as usize;
+        assert_eq!(ppm[pixel..pixel + 3], HEAT[0]);
+        assert_eq!(ppm[header.len()..header.len() + 3], UNKNOWN);
+    }
+}
@@ -3,6 +3,8 @@ use crate::point::Point;
 
 use std::collections::HashMap;
 
+const ORIGIN: Point = Point { x: 0, y: 0 };
+
 pub struct SectionMap {
     map: HashMap<Point, MoveResult>,
     x_min: i64,
@@ -32,6 +34,14 @@ impl SectionMap {
         self.map.insert(point, result);
     }
 
+    /// The corners of the explored area, south-west then north-east.
+    pub fn get_bounds(&self) -> (Point, Point) {
+        (
+            Point::at(self.x_min, self.y_min),
+            Point::at(self.x_max, self.y_max),
+        )
+    }
+
     pub fn get_result(&self, point: &Point) -> Option<MoveResult> {
         self.map.get(point).cloned()
     }
@@ -43,6 +53,26 @@ impl SectionMap {
         }
     }
 
+    /// Whether the droid can move through a cell, which includes the oxygen
+    /// system.
+    pub fn is_open(&self, point: &Point) -> bool {
+        matches!(
+            self.get_result(point),
+            Some(MoveResult::Empty) | Some(MoveResult::Oxygen)
+        )
+    }
+
+    pub fn is_wall(&self, point: &Point) -> bool {
+        matches!(self.get_result(point), Some(MoveResult::Wall))
+    }
+
+    pub fn get_oxygen_system(&self) -> Option<Point> {
+        self.map
+            .iter()
+            .find(|(_, result)| result.is_oxygen_system())
+            .map(|(&point, _)| point)
+    }
+
     pub fn get_neighbors(&self, point: Point) -> Vec<Point> {
         vec![
             point + Direction::North.to_vector(),
@@ -63,6 +93,14 @@ impl SectionMap {
             .collect::<Vec<_>>()
     }
 
+    pub fn get_open_neighbors(&self, point: Point) -> Vec<Point> {
+        self.get_neighbors(point)
+            .iter()
+            .filter(|point| self.is_open(point))
+            .cloned()
+            .collect::<Vec<_>>()
+    }
+
     pub fn to_string(&self, pos: Point) -> String {
         let mut output = String::new();
 
@@ -87,4 +125,161 @@ impl SectionMap {
 
         output
     }
+
+    /// The map as text that `load` reads back: the bounds and the oxygen
+    /// system's location, a blank line, then the grid with north at the top
+    /// and the start marked `S`.
+    pub fn save(&self) -> String {
+        let point = |point: Point| format!("{},{}", point.x, point.y);
+        let oxygen_system = self.get_oxygen_system().map_or("none".to_string(), point);
+        let mut output = format!(
+            "min {}\nmax {}\noxygen {}\n\n",
+            point(Point::at(self.x_min, self.y_min)),
+            point(Point::at(self.x_max, self.y_max)),
+            oxygen_system
+        );
+
+        for y in (self.y_min..=self.y_max).rev() {
+            for x in self.x_min..=self.x_max {
+                let point = Point::at(x, y);
+                output.push(match self.get_result(&point) {
+                    _ if point == ORIGIN => 'S',
+                    Some(MoveResult::Wall) => '#',
+                    Some(MoveResult::Empty) => '.',
+                    Some(MoveResult::Oxygen) => 'O',
+                    None => ' ',
+                });
+            }
+            output.push('\n');
+        }
+
+        output
+    }
+
+    pub fn load(text: &str) -> Result<SectionMap, String> {
+        let mut sections = text.splitn(2, "\n\n");
+        let header = sections.next().unwrap_or("");
+        let grid = sections.next().ok_or("missing grid")?;
+
+        let mut fields = HashMap::new();
+        for line in header.lines() {
+            let mut parts = line.splitn(2, ' ');
+            if let (Some(key), Some(value)) = (parts.next(), parts.next()) {
+                fields.insert(key, value);
+            }
+        }
+        let point = |key: &str| -> Result<Option<Point>, String> {
+            let value = fields.get(key).ok_or_else(|| format!("missing {}", key))?;
+            if *value == "none" {
+                return Ok(None);
+            }
+            let coords = value
+                .split(',')
+                .map(|n| n.parse::<i64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| format!("bad {} {:?}", key, value))?;
+            match coords.as_slice() {
+                &[x, y] => Ok(Some(Point::at(x, y))),
+                _ => Err(format!("bad {} {:?}", key, value)),
+            }
+        };
+        let min = point("min")?.ok_or("missing min")?;
+        let max = point("max")?.ok_or("missing max")?;
+        let oxygen_system = point("oxygen")?;
+
+        let rows = grid.lines().collect::<Vec<_>>();
+        let width = (max.x - min.x + 1) as usize;
+        if rows.len() as i64 != max.y - min.y + 1
+            || rows.iter().any(|row| row.chars().count() != width)
+        {
+            return Err(format!("grid is not {} by {}", width, max.y - min.y + 1));
+        }
+
+        let mut map = SectionMap::new();
+        let mut origin = None;
+        let mut oxygen_cells = 0;
+        for (row, y) in rows.iter().zip((min.y..=max.y).rev()) {
+            for (c, x) in row.chars().zip(min.x..=max.x) {
+                let point = Point::at(x, y);
+                let result = match c {
+                    '#' => MoveResult::Wall,
+                    '.' => MoveResult::Empty,
+                    'O' => {
+                        oxygen_cells += 1;
+                        MoveResult::Oxygen
+                    }
+                    'S' => {
+                        origin = Some(point);
+                        MoveResult::Empty
+                    }
+                    ' ' => continue,
+                    c => return Err(format!("unknown cell {:?} at {},{}", c, x, y)),
+                };
+                map.set_result(point, result);
+            }
+        }
+
+        if origin != Some(ORIGIN) {
+            return Err("the start is not at 0,0".to_string());
+        }
+        let expected = if oxygen_system.is_some() { 1 } else { 0 };
+        if oxygen_cells != expected {
+            return Err(format!(
+                "oxygen systems in the grid: {}, expected {}",
+                oxygen_cells, expected
+            ));
+        }
+        if map.get_oxygen_system() != oxygen_system {
+            return Err("oxygen system does not match the grid".to_string());
+        }
+        map.x_min = min.x;
+        map.x_max = max.x;
+        map.y_min = min.y;
+        map.y_max = max.y;
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::plan_path;
+
+    use std::fs;
+
+    #[test]
+    fn test_save_load() {
+        let text = fs::read_to_string("./section.map").unwrap();
+        let map = SectionMap::load(&text).unwrap();
+        assert_eq!(map.save(), text);
+
+        let oxygen_system = map.get_oxygen_system().unwrap();
+        assert_eq!(plan_path(oxygen_system, ORIGIN, &map).len(), 224);
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let load = |text| SectionMap::load(text).err().unwrap();
+        assert_eq!(load("min 0,0\nmax 1,0"), "missing grid");
+        assert_eq!(
+            load("min 0,0\nmax 1,0\noxygen none\n\nS\n"),
+            "grid is not 2 by 1"
+        );
+        assert_eq!(
+            load("min 0,0\nmax 1,0\noxygen none\n\nSx\n"),
+            "unknown cell 'x' at 1,0"
+        );
+        assert_eq!(
+            load("min 0,0\nmax 1,0\noxygen 0,0\n\nSO\n"),
+            "oxygen system does not match the grid"
+        );
+        assert_eq!(
+            load("min 0,0\nmax 2,0\noxygen 2,0\n\nSOO\n"),
+            "oxygen systems in the grid: 2, expected 1"
+        );
+        assert_eq!(
+            load("min 0,0\nmax 1,0\noxygen none\n\nSO\n"),
+            "oxygen systems in the grid: 1, expected 0"
+        );
+    }
 }
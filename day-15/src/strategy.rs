@@ -0,0 +1,264 @@
+//! Ways for the droid to decide where to explore next.
+
+use crate::droid::Direction;
+use crate::point::Point;
+use crate::section_map::SectionMap;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+pub trait Strategy {
+    fn name(&self) -> String;
+
+    /// The cell the droid should head for next, or `None` to stop
+    /// exploring. `frontier` holds the cells next to open ones that have not
+    /// been tried yet, newest first.
+    fn next_goal(
+        &mut self,
+        pos: Point,
+        frontier: &VecDeque<Point>,
+        map: &SectionMap,
+    ) -> Option<Point>;
+}
+
+/// Always heads for the newest cell on the frontier.
+pub struct DepthFirst;
+
+impl Strategy for DepthFirst {
+    fn name(&self) -> String {
+        "depth first".to_string()
+    }
+
+    fn next_goal(
+        &mut self,
+        _pos: Point,
+        frontier: &VecDeque<Point>,
+        _map: &SectionMap,
+    ) -> Option<Point> {
+        frontier.front().cloned()
+    }
+}
+
+/// Always heads for the oldest cell on the frontier.
+pub struct BreadthFirst;
+
+impl Strategy for BreadthFirst {
+    fn name(&self) -> String {
+        "breadth first".to_string()
+    }
+
+    fn next_goal(
+        &mut self,
+        _pos: Point,
+        frontier: &VecDeque<Point>,
+        _map: &SectionMap,
+    ) -> Option<Point> {
+        frontier.back().cloned()
+    }
+}
+
+/// Heads for whichever cell on the frontier is the fewest moves away.
+pub struct Nearest;
+
+impl Strategy for Nearest {
+    fn name(&self) -> String {
+        "nearest".to_string()
+    }
+
+    fn next_goal(
+        &mut self,
+        pos: Point,
+        frontier: &VecDeque<Point>,
+        map: &SectionMap,
+    ) -> Option<Point> {
+        let distances = get_distances(pos, map);
+        frontier
+            .iter()
+            .filter_map(|&cell| {
+                map.get_neighbors(cell)
+                    .iter()
+                    .filter_map(|neighbor| distances.get(neighbor))
+                    .min()
+                    .map(|distance| (distance + 1, cell))
+            })
+            .min_by_key(|&(distance, _)| distance)
+            .map(|(_, cell)| cell)
+    }
+}
+
+/// Moves through known open cells from `start`, counting the steps to each.
+fn get_distances(start: Point, map: &SectionMap) -> HashMap<Point, usize> {
+    let mut distances = HashMap::new();
+    distances.insert(start, 0);
+    let mut queue = VecDeque::new();
+    queue.push_back(start);
+
+    while let Some(current) = queue.pop_front() {
+        let distance = distances[&current] + 1;
+        for next in map.get_open_neighbors(current) {
+            distances.entry(next).or_insert_with(|| {
+                queue.push_back(next);
+                distance
+            });
+        }
+    }
+
+    distances
+}
+
+/// Keeps its left hand on the wall. This only reaches every cell when the
+/// walls have no loops, which holds for the repair droid's section. It stops
+/// once it arrives somewhere facing the same way as before, since from there
+/// it would only go round the same loop again.
+pub struct WallFollower {
+    heading: Direction,
+    last_pos: Point,
+    seen: HashSet<(Point, Direction)>,
+}
+
+impl Default for WallFollower {
+    fn default() -> Self {
+        WallFollower {
+            heading: Direction::North,
+            last_pos: Point::at(0, 0),
+            seen: HashSet::new(),
+        }
+    }
+}
+
+fn turn_left(dir: Direction) -> Direction {
+    match dir {
+        Direction::North => Direction::West,
+        Direction::West => Direction::South,
+        Direction::South => Direction::East,
+        Direction::East => Direction::North,
+    }
+}
+
+fn turn_right(dir: Direction) -> Direction {
+    match dir {
+        Direction::North => Direction::East,
+        Direction::East => Direction::South,
+        Direction::South => Direction::West,
+        Direction::West => Direction::North,
+    }
+}
+
+impl Strategy for WallFollower {
+    fn name(&self) -> String {
+        "wall follower".to_string()
+    }
+
+    fn next_goal(
+        &mut self,
+        pos: Point,
+        _frontier: &VecDeque<Point>,
+        map: &SectionMap,
+    ) -> Option<Point> {
+        if pos != self.last_pos {
+            self.heading = Direction::from_vector(&(pos - self.last_pos));
+            self.last_pos = pos;
+            if !self.seen.insert((pos, self.heading)) {
+                return None;
+            }
+        }
+
+        // Left, ahead, right, then back the way it came.
+        let mut dir = turn_left(self.heading);
+        for _ in 0..4 {
+            let next = pos + dir.to_vector();
+            if !map.is_wall(&next) {
+                return Some(next);
+            }
+            dir = turn_right(dir);
+        }
+        None
+    }
+}
+
+/// Explores with another strategy until the oxygen system turns up.
+pub struct UntilOxygen<S: Strategy>(pub S);
+
+impl<S: Strategy> Strategy for UntilOxygen<S> {
+    fn name(&self) -> String {
+        format!("{} until oxygen", self.0.name())
+    }
+
+    fn next_goal(
+        &mut self,
+        pos: Point,
+        frontier: &VecDeque<Point>,
+        map: &SectionMap,
+    ) -> Option<Point> {
+        if map.get_oxygen_system().is_some() {
+            None
+        } else {
+            self.0.next_goal(pos, frontier, map)
+        }
+    }
+}
+
+/// One of each strategy, for comparing them.
+pub fn all_strategies() -> Vec<Box<dyn Strategy>> {
+    vec![
+        Box::new(DepthFirst),
+        Box::new(BreadthFirst),
+        Box::new(Nearest),
+        Box::new(WallFollower::default()),
+        Box::new(UntilOxygen(Nearest)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::Ai;
+    use crate::droid::Droid;
+    use intcode::{load_program, Computer};
+
+    use std::fs;
+
+    #[test]
+    fn test_strategies() {
+        let mem = load_program("./repair.intcode");
+        let full_map = fs::read_to_string("./section.map").unwrap();
+
+        for strategy in all_strategies() {
+            let name = strategy.name();
+            let mut droid = Droid::new(Computer::new(&mem));
+            let mut map = SectionMap::new();
+            let mut ai = Ai::with_strategy(&map, strategy);
+            while !ai.done_exploring() {
+                ai.update(&mut droid, &mut map);
+            }
+
+            let stats = ai.get_stats();
+            assert_eq!(stats.moves, droid.get_moves(), "{}", name);
+            assert_eq!(ai.get_path_length(&map), 224, "{}", name);
+            if name.ends_with("until oxygen") {
+                assert!(map.save() != full_map, "{}", name);
+            } else {
+                assert_eq!(map.save(), full_map, "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_wall_follower_stops_going_round_a_loop() {
+        // Eight open cells around a wall, so the follower would circle it
+        // forever.
+        let map = SectionMap::load(
+            "min -1,-1\nmax 3,3\noxygen none\n\n#####\n#...#\n#.#.#\n#S..#\n#####\n",
+        )
+        .unwrap();
+        let mut follower = WallFollower::default();
+        let mut pos = Point::at(0, 0);
+        let mut moves = 0;
+        while let Some(goal) = follower.next_goal(pos, &VecDeque::new(), &map) {
+            assert!(!map.is_wall(&goal));
+            pos = goal;
+            moves += 1;
+            assert!(moves < 100, "still going after {} moves", moves);
+        }
+        assert_eq!(moves, 9);
+    }
+}
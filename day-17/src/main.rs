@@ -1,16 +1,41 @@
-mod intcode;
+mod path;
+mod scaffold;
 mod vector;
+mod video;
 
 use intcode::{load_program, Computer};
-use vector::Vector;
+use scaffold::ScaffoldMap;
 
 use std::char;
-use std::collections::HashSet;
+use std::env;
 use std::io::{self, Write};
+use std::process;
+use std::time::Duration;
+
+const DEFAULT_FPS: f64 = 20.0;
 
 fn main() {
+    // `video [FPS]` plays the robot's continuous video feed in part 2.
+    let args = env::args().collect::<Vec<_>>();
+    let fps = match args.get(1).map(String::as_str) {
+        Some("video") => Some(args.get(2).map_or(DEFAULT_FPS, |fps| {
+            parse_fps(fps).unwrap_or_else(|| {
+                eprintln!("usage: video [FPS], where FPS is a positive frame rate");
+                process::exit(1);
+            })
+        })),
+        _ => None,
+    };
+
     part1();
-    part2();
+    part2(fps);
+}
+
+/// A frame rate `video::play` can turn into a delay between frames.
+fn parse_fps(text: &str) -> Option<f64> {
+    text.parse::<f64>()
+        .ok()
+        .filter(|&fps| fps > 0.0 && Duration::try_from_secs_f64(1.0 / fps).is_ok())
 }
 
 fn part1() {
@@ -19,107 +44,70 @@ fn part1() {
     cpu.run();
 
     let map = print_video(&mut cpu);
-
-    let alignment: i64 = map
-        .iter()
-        .filter(|&point| is_intersection(*point, &map))
-        .map(|&point| alignment_param(point))
-        .sum();
-    println!("part 1: {}", alignment);
+    println!("part 1: {}", map.alignment_sum());
 }
 
-fn print_video(cpu: &mut Computer) -> HashSet<Vector<i64>> {
-    let mut x = 0;
-    let mut y = 0;
-
-    let mut map = HashSet::<Vector<i64>>::new();
+fn print_video(cpu: &mut Computer) -> ScaffoldMap {
     let mut output = String::new();
-
     while let Some(data) = cpu.get_output() {
         let pixel =
             char::from_u32(data as u32).expect("Failed to get character for intcode output");
         output.push(pixel);
-
-        match pixel {
-            '#' => {
-                map.insert(Vector::new(x, y));
-            }
-            _ => (),
-        };
-
-        if pixel == '\n' {
-            x = 0;
-            y += 1;
-        } else {
-            x += 1;
-        }
     }
 
     println!("{}", output);
 
-    map
-}
-
-fn is_intersection(point: Vector<i64>, map: &HashSet<Vector<i64>>) -> bool {
-    let north = point + Vector::new(-1, 0);
-    let south = point + Vector::new(1, 0);
-    let east = point + Vector::new(0, 1);
-    let west = point + Vector::new(0, -1);
-
-    map.contains(&north) && map.contains(&south) && map.contains(&east) && map.contains(&west)
-}
-
-fn alignment_param(intersection: Vector<i64>) -> i64 {
-    intersection.x * intersection.y
+    ScaffoldMap::from_string(&output)
 }
 
-fn part2() {
+fn part2(fps: Option<f64>) {
     let mut mem = load_program("./input.intcode");
     mem[0] = 2;
     let mut cpu = Computer::new(&mem);
 
     cpu.run();
-    flush_output(&mut cpu);
-    send_input(&mut cpu, "A,B,A,C,B,A,C,B,A,C\n");
-
-    cpu.run();
-    flush_output(&mut cpu);
-    send_input(&mut cpu, "L,6,L,4,R,12\n");
-
-    cpu.run();
-    flush_output(&mut cpu);
-    send_input(&mut cpu, "L,6,R,12,R,12,L,8\n");
+    let map = print_video(&mut cpu);
+    let path = path::walk(&map);
+    let routines = path::compress(&path).expect("Path does not fit in the robot's memory");
+    let main_routine = routines.main_routine();
+    let functions = (0..3)
+        .map(|function| routines.function(function))
+        .collect::<Vec<_>>();
+    let function_refs = functions.iter().map(String::as_str).collect::<Vec<_>>();
+    if let Err(error) = scaffold::run_routines(&map, &main_routine, &function_refs) {
+        panic!("Routines fail on the local simulator: {}", error);
+    }
+    send_input(&mut cpu, &format!("{}\n", main_routine));
 
-    cpu.run();
-    flush_output(&mut cpu);
-    send_input(&mut cpu, "L,6,L,10,L,10,L,6\n");
+    for function in &functions {
+        cpu.run();
+        flush_output(&mut cpu);
+        send_input(&mut cpu, &format!("{}\n", function));
+    }
 
     cpu.run();
     flush_output(&mut cpu);
-    send_input(&mut cpu, "n\n");
+    send_input(&mut cpu, if fps.is_some() { "y\n" } else { "n\n" });
 
     cpu.run();
 
-    let mut result = 0;
-    loop {
-        if let Some(data) = cpu.get_output() {
-            let pixel = char::from_u32(data as u32).unwrap();
-            match pixel {
-                '.' => (),
-                '#' => (),
-                '^' => (),
-                '>' => (),
-                'v' => (),
-                '<' => (),
-                '\n' => (),
-                _ => result = data,
-            };
-        } else {
-            break;
+    let mut output = Vec::new();
+    while let Some(data) = cpu.get_output() {
+        output.push(data);
+    }
+    let feed = match video::read_feed(&output) {
+        Ok(feed) => feed,
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
         }
+    };
+    if let Some(fps) = fps {
+        video::play(&feed.frames, fps);
     }
 
-    println!("part 2: {:?}", result);
+    let dust = feed.dust.expect("No dust total in the robot's output");
+    println!("part 2: {}", dust);
 }
 
 fn flush_output(cpu: &mut Computer) {
@@ -0,0 +1,226 @@
+//! Turns the scaffold into the robot's movement routines: first the whole
+//! path as turns and distances, then a split of that path into a main
+//! routine calling up to three movement functions.
+
+use crate::scaffold::{turn_left, turn_right, ScaffoldMap, FUNCTION_NAMES, MAX_ROUTINE_LENGTH};
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Turn {
+    Left,
+    Right,
+    /// No turn, for a robot that already faces along the scaffold.
+    Straight,
+    /// Two turns, for a robot that starts with its back to the scaffold.
+    Around,
+}
+
+/// A turn followed by a run forward.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Move {
+    pub turn: Turn,
+    pub distance: usize,
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.turn {
+            Turn::Left => write!(f, "L,{}", self.distance),
+            Turn::Right => write!(f, "R,{}", self.distance),
+            Turn::Straight => write!(f, "{}", self.distance),
+            Turn::Around => write!(f, "R,R,{}", self.distance),
+        }
+    }
+}
+
+/// Follows the scaffold from the robot, going straight over intersections
+/// and turning wherever the scaffold does, until it runs out. Only the
+/// first move can go straight on or turn around, since after every run the
+/// scaffold ahead has ended and the scaffold behind is where the robot came
+/// from.
+pub fn walk(map: &ScaffoldMap) -> Vec<Move> {
+    let robot = map.robot.expect("No robot on the scaffold");
+    let mut position = robot.position;
+    let mut facing = robot.facing.expect("The robot is tumbling through space");
+    let mut moves = Vec::new();
+    loop {
+        let starting = moves.is_empty();
+        let behind = turn_left(turn_left(facing));
+        let turn = if starting && map.is_scaffold(position + facing) {
+            Turn::Straight
+        } else if map.is_scaffold(position + turn_left(facing)) {
+            facing = turn_left(facing);
+            Turn::Left
+        } else if map.is_scaffold(position + turn_right(facing)) {
+            facing = turn_right(facing);
+            Turn::Right
+        } else if starting && map.is_scaffold(position + behind) {
+            facing = behind;
+            Turn::Around
+        } else {
+            return moves;
+        };
+
+        let mut distance = 0;
+        while map.is_scaffold(position + facing) {
+            position += facing;
+            distance += 1;
+        }
+        moves.push(Move { turn, distance });
+    }
+}
+
+fn join<T: ToString>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// A path split into a main routine and the movement functions it calls.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Routines {
+    /// Indexes into `functions`.
+    pub main: Vec<usize>,
+    pub functions: Vec<Vec<Move>>,
+}
+
+impl Routines {
+    pub fn main_routine(&self) -> String {
+        join(
+            &self
+                .main
+                .iter()
+                .map(|&function| FUNCTION_NAMES[function])
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Functions A, B and C in order. Functions the path does not need are
+    /// left empty.
+    pub fn function(&self, index: usize) -> String {
+        self.functions
+            .get(index)
+            .map_or_else(String::new, |moves| join(moves))
+    }
+
+    /// Every move the robot makes, with the function calls expanded.
+    pub fn expand(&self) -> Vec<Move> {
+        self.main
+            .iter()
+            .flat_map(|&function| self.functions[function].iter().copied())
+            .collect()
+    }
+}
+
+fn compress_from(path: &[Move], routines: &mut Routines) -> bool {
+    if path.is_empty() {
+        return true;
+    }
+    // Every call adds a name and a comma.
+    if routines.main.len() * 2 + 1 > MAX_ROUTINE_LENGTH {
+        return false;
+    }
+
+    for function in 0..routines.functions.len() {
+        let moves = &routines.functions[function];
+        if path.starts_with(moves) {
+            let rest = &path[moves.len()..];
+            routines.main.push(function);
+            if compress_from(rest, routines) {
+                return true;
+            }
+            routines.main.pop();
+        }
+    }
+
+    if routines.functions.len() < FUNCTION_NAMES.len() {
+        let mut length = 0;
+        while length < path.len() && join(&path[..=length]).len() <= MAX_ROUTINE_LENGTH {
+            length += 1;
+            routines.functions.push(path[..length].to_vec());
+            routines.main.push(routines.functions.len() - 1);
+            if compress_from(&path[length..], routines) {
+                return true;
+            }
+            routines.main.pop();
+            routines.functions.pop();
+        }
+    }
+    false
+}
+
+/// Splits a path into a main routine and up to three movement functions
+/// that all fit in the robot's memory, or `None` if there is no such split.
+pub fn compress(path: &[Move]) -> Option<Routines> {
+    let mut routines = Routines {
+        main: Vec::new(),
+        functions: Vec::new(),
+    };
+    if compress_from(path, &mut routines) {
+        debug_assert_eq!(routines.expand(), path);
+        Some(routines)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> ScaffoldMap {
+        ScaffoldMap::from_string(include_str!("../example.txt"))
+    }
+
+    fn path_string(moves: &[Move]) -> String {
+        join(moves)
+    }
+
+    #[test]
+    fn test_walk() {
+        let path = walk(&example());
+        assert_eq!(
+            path_string(&path),
+            "R,8,R,8,R,4,R,4,R,8,L,6,L,2,R,4,R,4,R,8,R,8,R,8,L,6,L,2"
+        );
+    }
+
+    #[test]
+    fn test_walk_starting_along_the_scaffold() {
+        let map = ScaffoldMap::from_string(">###\n...#\n...#\n");
+        assert_eq!(path_string(&walk(&map)), "3,R,2");
+    }
+
+    #[test]
+    fn test_walk_starting_backwards() {
+        let map = ScaffoldMap::from_string("<###\n...#\n...#\n");
+        assert_eq!(path_string(&walk(&map)), "R,R,3,R,2");
+    }
+
+    #[test]
+    fn test_compress() {
+        let path = walk(&example());
+        let routines = compress(&path).unwrap();
+        assert_eq!(routines.expand(), path);
+        assert!(routines.main_routine().len() <= MAX_ROUTINE_LENGTH);
+        for function in 0..3 {
+            assert!(routines.function(function).len() <= MAX_ROUTINE_LENGTH);
+        }
+    }
+
+    #[test]
+    fn test_compress_gives_up() {
+        // A function holds at most four of these moves, so thirteen
+        // different ones are too many for three functions.
+        let path = (10..23)
+            .map(|distance| Move {
+                turn: Turn::Left,
+                distance,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(compress(&path), None);
+    }
+}
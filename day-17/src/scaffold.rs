@@ -0,0 +1,356 @@
+//! The scaffold as the robot's camera shows it, and a local run of the
+//! robot's movement routines over it.
+
+use crate::vector::Vector;
+
+use std::collections::HashSet;
+use std::fmt;
+
+/// The robot's memory only holds this many characters per routine, not
+/// counting the newline.
+pub const MAX_ROUTINE_LENGTH: usize = 20;
+
+pub const FUNCTION_NAMES: [&str; 3] = ["A", "B", "C"];
+
+const DIRECTIONS: [(char, Vector<i64>); 4] = [
+    ('^', Vector { x: 0, y: -1 }),
+    ('v', Vector { x: 0, y: 1 }),
+    ('<', Vector { x: -1, y: 0 }),
+    ('>', Vector { x: 1, y: 0 }),
+];
+
+/// Whether `c` can show up in a camera frame.
+pub fn is_pixel(c: char) -> bool {
+    c == '#' || c == '.' || c == 'X' || DIRECTIONS.iter().any(|&(robot, _)| robot == c)
+}
+
+pub fn turn_left(facing: Vector<i64>) -> Vector<i64> {
+    Vector::new(facing.y, -facing.x)
+}
+
+pub fn turn_right(facing: Vector<i64>) -> Vector<i64> {
+    Vector::new(-facing.y, facing.x)
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Robot {
+    pub position: Vector<i64>,
+    /// `None` while the robot is tumbling through space, shown as `X`.
+    pub facing: Option<Vector<i64>>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ScaffoldMap {
+    pub scaffold: HashSet<Vector<i64>>,
+    pub robot: Option<Robot>,
+    pub width: i64,
+    pub height: i64,
+}
+
+impl ScaffoldMap {
+    /// Parses one camera frame, which ends at the first blank line.
+    pub fn from_string(text: &str) -> Self {
+        let mut scaffold = HashSet::new();
+        let mut robot = None;
+        let mut width = 0;
+        let mut height = 0;
+
+        let lines = text
+            .lines()
+            .skip_while(|line| line.is_empty())
+            .take_while(|line| !line.is_empty());
+        for (y, line) in lines.enumerate() {
+            for (x, pixel) in line.chars().enumerate() {
+                let position = Vector::new(x as i64, y as i64);
+                match pixel {
+                    '#' => {
+                        scaffold.insert(position);
+                    }
+                    '.' => (),
+                    'X' => {
+                        robot = Some(Robot {
+                            position,
+                            facing: None,
+                        })
+                    }
+                    _ => {
+                        let facing = DIRECTIONS
+                            .iter()
+                            .find(|(c, _)| *c == pixel)
+                            .map(|&(_, facing)| facing)
+                            .unwrap_or_else(|| panic!("Unexpected video pixel {:?}", pixel));
+                        // The robot always starts out on the scaffold.
+                        scaffold.insert(position);
+                        robot = Some(Robot {
+                            position,
+                            facing: Some(facing),
+                        });
+                    }
+                }
+            }
+            width = width.max(line.chars().count() as i64);
+            height = y as i64 + 1;
+        }
+
+        ScaffoldMap {
+            scaffold,
+            robot,
+            width,
+            height,
+        }
+    }
+
+    pub fn is_scaffold(&self, point: Vector<i64>) -> bool {
+        self.scaffold.contains(&point)
+    }
+
+    pub fn is_intersection(&self, point: Vector<i64>) -> bool {
+        self.is_scaffold(point)
+            && DIRECTIONS
+                .iter()
+                .all(|&(_, direction)| self.is_scaffold(point + direction))
+    }
+
+    pub fn intersections(&self) -> impl Iterator<Item = Vector<i64>> + '_ {
+        self.scaffold
+            .iter()
+            .copied()
+            .filter(move |&point| self.is_intersection(point))
+    }
+
+    pub fn alignment_sum(&self) -> i64 {
+        self.intersections().map(|point| point.x * point.y).sum()
+    }
+}
+
+impl fmt::Display for ScaffoldMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let point = Vector::new(x, y);
+                let pixel = match self.robot {
+                    Some(robot) if robot.position == point => match robot.facing {
+                        Some(facing) => DIRECTIONS.iter().find(|(_, d)| *d == facing).unwrap().0,
+                        None => 'X',
+                    },
+                    _ if self.is_scaffold(point) => '#',
+                    _ => '.',
+                };
+                write!(f, "{}", pixel)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum RoutineError {
+    TooLong(String),
+    BadCommand(String, String),
+    NotOnScaffold,
+    LeftScaffold(Vector<i64>),
+    Missed(Vec<Vector<i64>>),
+}
+
+impl fmt::Display for RoutineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TooLong(routine) => write!(
+                f,
+                "{} is longer than {} characters",
+                routine, MAX_ROUTINE_LENGTH
+            ),
+            Self::BadCommand(routine, command) => {
+                write!(f, "{} has a bad command {:?}", routine, command)
+            }
+            Self::NotOnScaffold => write!(f, "the robot is not on the scaffold"),
+            Self::LeftScaffold(point) => {
+                write!(
+                    f,
+                    "the robot leaves the scaffold at {},{}",
+                    point.x, point.y
+                )
+            }
+            Self::Missed(points) => write!(
+                f,
+                "the robot misses {} scaffold tiles, starting with {},{}",
+                points.len(),
+                points[0].x,
+                points[0].y
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Command {
+    Left,
+    Right,
+    Forward(i64),
+}
+
+fn parse_commands(name: &str, routine: &str) -> Result<Vec<Command>, RoutineError> {
+    if routine.len() > MAX_ROUTINE_LENGTH {
+        return Err(RoutineError::TooLong(name.to_string()));
+    }
+    routine
+        .split(',')
+        .filter(|command| !command.is_empty())
+        .map(|command| match command {
+            "L" => Ok(Command::Left),
+            "R" => Ok(Command::Right),
+            _ => command
+                .parse()
+                .map(Command::Forward)
+                .map_err(|_| RoutineError::BadCommand(name.to_string(), command.to_string())),
+        })
+        .collect()
+}
+
+/// Runs the main routine and movement functions the way the robot would,
+/// checking that it stays on the scaffold and visits all of it. Returns
+/// where the robot ends up.
+pub fn run_routines(
+    map: &ScaffoldMap,
+    main: &str,
+    functions: &[&str],
+) -> Result<Robot, RoutineError> {
+    if main.len() > MAX_ROUTINE_LENGTH {
+        return Err(RoutineError::TooLong("main".to_string()));
+    }
+    let functions = functions
+        .iter()
+        .zip(&FUNCTION_NAMES)
+        .map(|(routine, name)| parse_commands(name, routine))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (mut position, mut facing) = match map.robot {
+        Some(Robot {
+            position,
+            facing: Some(facing),
+        }) => (position, facing),
+        _ => return Err(RoutineError::NotOnScaffold),
+    };
+    let mut visited = HashSet::new();
+    visited.insert(position);
+
+    for call in main.split(',').filter(|call| !call.is_empty()) {
+        let commands = FUNCTION_NAMES
+            .iter()
+            .position(|name| *name == call)
+            .and_then(|function| functions.get(function))
+            .ok_or_else(|| RoutineError::BadCommand("main".to_string(), call.to_string()))?;
+        for &command in commands {
+            match command {
+                Command::Left => facing = turn_left(facing),
+                Command::Right => facing = turn_right(facing),
+                Command::Forward(distance) => {
+                    for _ in 0..distance {
+                        position += facing;
+                        if !map.is_scaffold(position) {
+                            return Err(RoutineError::LeftScaffold(position));
+                        }
+                        visited.insert(position);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut missed = map
+        .scaffold
+        .difference(&visited)
+        .copied()
+        .collect::<Vec<_>>();
+    if !missed.is_empty() {
+        missed.sort_by_key(|point| (point.y, point.x));
+        return Err(RoutineError::Missed(missed));
+    }
+    Ok(Robot {
+        position,
+        facing: Some(facing),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The example camera frames from parts 1 and 2 of the puzzle.
+    const CAMERA: &str = "\
+..#..........
+..#..........
+#######...###
+#.#...#...#.#
+#############
+..#...#...#..
+..#####...^..
+
+";
+    const EXAMPLE: &str = include_str!("../example.txt");
+
+    #[test]
+    fn test_camera_frame() {
+        let map = ScaffoldMap::from_string(CAMERA);
+        assert_eq!((map.width, map.height), (13, 7));
+        assert_eq!(
+            map.robot,
+            Some(Robot {
+                position: Vector::new(10, 6),
+                facing: Some(Vector::new(0, -1)),
+            })
+        );
+        assert!(map.is_scaffold(Vector::new(10, 6)));
+        assert_eq!(map.intersections().count(), 4);
+        assert_eq!(map.alignment_sum(), 76);
+        assert_eq!(map.to_string(), CAMERA.trim_end().to_string() + "\n");
+    }
+
+    #[test]
+    fn test_tumbling_robot() {
+        let map = ScaffoldMap::from_string("..#..\n.#X#.\n..#..\n\nMain:\n");
+        assert_eq!(map.height, 3);
+        assert_eq!(map.robot.unwrap().facing, None);
+        assert!(!map.is_scaffold(Vector::new(2, 1)));
+        assert_eq!(map.intersections().count(), 0);
+        assert_eq!(
+            run_routines(&map, "A", &["L,1", "", ""]),
+            Err(RoutineError::NotOnScaffold)
+        );
+    }
+
+    #[test]
+    fn test_run_routines() {
+        let map = ScaffoldMap::from_string(EXAMPLE);
+        let functions = ["R,8,R,8", "R,4,R,4,R,8", "L,6,L,2"];
+        let robot = run_routines(&map, "A,B,C,B,A,C", &functions).unwrap();
+        assert_eq!(robot.position, Vector::new(0, 2));
+
+        assert_eq!(
+            run_routines(&map, "A,B,C,B,A", &functions)
+                .unwrap_err()
+                .to_string(),
+            "the robot misses 8 scaffold tiles, starting with 0,0"
+        );
+        assert_eq!(
+            run_routines(&map, "A,B,C,C", &functions),
+            Err(RoutineError::LeftScaffold(Vector::new(10, 5)))
+        );
+        assert_eq!(
+            run_routines(&map, "A,D", &functions),
+            Err(RoutineError::BadCommand(
+                "main".to_string(),
+                "D".to_string()
+            ))
+        );
+        assert_eq!(
+            run_routines(&map, "A", &["R,8,R,8,R,4,R,4,R,8,L,6", "", ""]),
+            Err(RoutineError::TooLong("A".to_string()))
+        );
+        assert_eq!(
+            run_routines(&map, "A", &["R,8,X", "", ""]),
+            Err(RoutineError::BadCommand("A".to_string(), "X".to_string()))
+        );
+    }
+}
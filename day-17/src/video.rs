@@ -0,0 +1,95 @@
+//! The robot's continuous video feed: camera frames separated by blank
+//! lines, with the dust total as the one value that is not ASCII.
+
+use crate::scaffold::{is_pixel, ScaffoldMap};
+
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+/// Moves the cursor home and clears the terminal.
+const CLEAR_SCREEN: &str = "\x1b[H\x1b[2J";
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Feed {
+    pub frames: Vec<ScaffoldMap>,
+    /// Everything that is not a frame, like the robot's prompts.
+    pub text: String,
+    pub dust: Option<i64>,
+}
+
+/// Splits the robot's output into frames, text and the dust total. Fails if
+/// more than one value is not ASCII, since only one can be the total.
+pub fn read_feed(output: &[i64]) -> Result<Feed, String> {
+    let mut ascii = String::new();
+    let mut dust = None;
+    for &data in output {
+        if (0..128).contains(&data) {
+            ascii.push(data as u8 as char);
+        } else if let Some(previous) = dust.replace(data) {
+            return Err(format!(
+                "found non-ASCII values {} and {} in the video feed",
+                previous, data
+            ));
+        }
+    }
+
+    let mut frames = Vec::new();
+    let mut text = String::new();
+    for chunk in ascii.split("\n\n") {
+        let chunk = chunk.trim_matches('\n');
+        if chunk.is_empty() {
+            continue;
+        }
+        if chunk.chars().all(|c| c == '\n' || is_pixel(c)) {
+            frames.push(ScaffoldMap::from_string(chunk));
+        } else {
+            text.push_str(chunk);
+            text.push('\n');
+        }
+    }
+
+    Ok(Feed { frames, text, dust })
+}
+
+/// Draws each frame over the last one, `fps` times a second.
+pub fn play(frames: &[ScaffoldMap], fps: f64) {
+    let delay = Duration::from_secs_f64(1.0 / fps);
+    for (i, frame) in frames.iter().enumerate() {
+        println!("{}{}frame {}/{}", CLEAR_SCREEN, frame, i + 1, frames.len());
+        io::stdout().flush().unwrap();
+        thread::sleep(delay);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ascii(text: &str) -> Vec<i64> {
+        text.chars().map(|c| c as i64).collect()
+    }
+
+    #[test]
+    fn test_read_feed() {
+        let mut output = ascii("Continuous video feed?\n\n#^#\n...\n\n#>#\n...\n\n.X.\n#.#\n\n");
+        output.push(1234);
+        let feed = read_feed(&output).unwrap();
+
+        assert_eq!(feed.frames.len(), 3);
+        assert_eq!(feed.frames[1].to_string(), "#>#\n...\n");
+        assert_eq!(feed.frames[2].robot.unwrap().facing, None);
+        assert_eq!(feed.text, "Continuous video feed?\n");
+        assert_eq!(feed.dust, Some(1234));
+
+        assert_eq!(read_feed(&ascii("#^#\n")).unwrap().dust, None);
+    }
+
+    #[test]
+    fn test_read_feed_with_two_totals() {
+        assert_eq!(
+            read_feed(&[1234, 10, 5678]),
+            Err("found non-ASCII values 1234 and 5678 in the video feed".to_string())
+        );
+    }
+}
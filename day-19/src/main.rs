@@ -1,4 +1,3 @@
-mod intcode;
 mod point;
 
 use intcode::{load_program, Computer, Memory};
@@ -1,5 +1,10 @@
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
-enum Register {
+use std::fmt;
+
+/// The droid's memory only holds this many instructions.
+pub const MAX_INSTRUCTIONS: usize = 15;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Register {
     T,
     J,
     A,
@@ -14,7 +19,7 @@ enum Register {
 }
 
 impl Register {
-    fn from_string(text: &str) -> Option<Self> {
+    pub fn from_string(text: &str) -> Option<Self> {
         match text {
             "T" => Some(Self::T),
             "J" => Some(Self::J),
@@ -30,10 +35,25 @@ impl Register {
             _ => None,
         }
     }
+
+    pub fn is_writable(self) -> bool {
+        self == Self::T || self == Self::J
+    }
+
+    /// Sensors five to nine tiles away are only switched on by `RUN`.
+    pub fn is_run_only(self) -> bool {
+        matches!(self, Self::E | Self::F | Self::G | Self::H | Self::I)
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
-enum Instruction {
+pub enum Instruction {
     And(Register, Register),
     Or(Register, Register),
     Not(Register, Register),
@@ -61,11 +81,173 @@ impl Instruction {
     }
 }
 
-fn parse_script(script: &str) -> Vec<Instruction> {
-    script
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::And(x, y) => write!(f, "AND {} {}", x, y),
+            Self::Or(x, y) => write!(f, "OR {} {}", x, y),
+            Self::Not(x, y) => write!(f, "NOT {} {}", x, y),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Mode {
+    Walk,
+    Run,
+}
+
+impl Mode {
+    pub fn from_string(text: &str) -> Option<Self> {
+        match text {
+            "WALK" => Some(Self::Walk),
+            "RUN" => Some(Self::Run),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Walk => write!(f, "WALK"),
+            Self::Run => write!(f, "RUN"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Script {
+    pub instructions: Vec<Instruction>,
+    pub mode: Mode,
+}
+
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for ins in &self.instructions {
+            writeln!(f, "{}", ins)?;
+        }
+        writeln!(f, "{}", self.mode)
+    }
+}
+
+/// A problem in a springscript source. Lines and columns count from 1.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ScriptError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// Splits a line into words along with the column each starts at.
+fn words(line: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in line.char_indices().chain(Some((line.len(), ' '))) {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(i),
+            (Some(s), true) => {
+                words.push((s + 1, &line[s..i]));
+                start = None;
+            }
+            _ => (),
+        }
+    }
+    words
+}
+
+/// Checks a script against the droid's rules and parses it. Blank lines
+/// are skipped, since the script is sent back to the droid one
+/// instruction at a time.
+pub fn parse_script(script: &str) -> Result<Script, ScriptError> {
+    let lines = script
         .lines()
-        .map(|line| Instruction::from_string(line).expect("Failed to parse instruction"))
-        .collect::<Vec<_>>()
+        .enumerate()
+        .map(|(i, line)| (i + 1, words(line)))
+        .filter(|(_, words)| !words.is_empty())
+        .collect::<Vec<_>>();
+    let error = |line, column, message: String| {
+        Err(ScriptError {
+            line,
+            column,
+            message,
+        })
+    };
+
+    let mode = match lines.last() {
+        Some((line, words)) => match Mode::from_string(words[0].1) {
+            Some(mode) if words.len() == 1 => mode,
+            Some(mode) => return error(*line, words[1].0, format!("{} takes no registers", mode)),
+            None => return error(line + 1, 1, "script must end with WALK or RUN".to_string()),
+        },
+        None => return error(1, 1, "script must end with WALK or RUN".to_string()),
+    };
+
+    let mut instructions = Vec::new();
+    for (line, words) in &lines[..lines.len() - 1] {
+        let (column, op) = words[0];
+        if let Some(mode) = Mode::from_string(op) {
+            return error(*line, column, format!("{} must be the last line", mode));
+        }
+        if instructions.len() == MAX_INSTRUCTIONS {
+            return error(
+                *line,
+                column,
+                format!("scripts are limited to {} instructions", MAX_INSTRUCTIONS),
+            );
+        }
+        if op != "AND" && op != "OR" && op != "NOT" {
+            return error(
+                *line,
+                column,
+                format!("unknown instruction {}, expected AND, OR or NOT", op),
+            );
+        }
+        if words.len() != 3 {
+            let column = words.get(3).map_or_else(
+                || {
+                    words
+                        .last()
+                        .map(|(column, word)| column + word.len())
+                        .unwrap()
+                },
+                |(column, _)| *column,
+            );
+            return error(*line, column, format!("{} takes two registers", op));
+        }
+
+        for (i, &(column, name)) in words[1..].iter().enumerate() {
+            let register = match Register::from_string(name) {
+                Some(register) => register,
+                None => return error(*line, column, format!("unknown register {}", name)),
+            };
+            if i == 1 && !register.is_writable() {
+                return error(
+                    *line,
+                    column,
+                    format!("cannot write to {}, only T and J are writable", register),
+                );
+            }
+            if mode == Mode::Walk && register.is_run_only() {
+                return error(
+                    *line,
+                    column,
+                    format!("{} can only be read in RUN mode", register),
+                );
+            }
+        }
+
+        let text = words.iter().map(|(_, word)| *word).collect::<Vec<_>>();
+        instructions.push(Instruction::from_string(&text.join(" ")).unwrap());
+    }
+
+    Ok(Script { instructions, mode })
 }
 
 #[cfg(test)]
@@ -76,16 +258,18 @@ mod tests {
 
     #[test]
     fn test_parse_script() {
-        let ins = parse_script(
+        let script = parse_script(
             "NOT A J
              NOT B T
              OR T J
              NOT C T
              OR T J
-             AND D J",
-        );
+             AND D J
+             WALK",
+        )
+        .unwrap();
         assert_eq!(
-            ins,
+            script.instructions,
             vec![
                 Not(A, J),
                 Not(B, T),
@@ -94,6 +278,60 @@ mod tests {
                 Or(T, J),
                 And(D, J)
             ]
-        )
+        );
+        assert_eq!(script.mode, Mode::Walk);
+        assert_eq!(script.to_string().lines().last(), Some("WALK"));
+    }
+
+    #[test]
+    fn test_solutions_are_valid() {
+        let part1 = parse_script(include_str!("../part1.springscript")).unwrap();
+        assert_eq!(part1.mode, Mode::Walk);
+        let part2 = parse_script(include_str!("../part2.springscript")).unwrap();
+        assert_eq!(part2.mode, Mode::Run);
+        assert_eq!(part2.instructions.len(), 10);
+    }
+
+    fn parse_error(script: &str) -> String {
+        parse_script(script).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_script_errors() {
+        assert_eq!(
+            parse_error("NOT A J\n"),
+            "2:1: script must end with WALK or RUN"
+        );
+        assert_eq!(parse_error(""), "1:1: script must end with WALK or RUN");
+        assert_eq!(
+            parse_error("NOT A J\nWALK\nNOT B T\nRUN"),
+            "2:1: WALK must be the last line"
+        );
+        assert_eq!(
+            parse_error("NOT A J\n  XOR A J\nWALK"),
+            "2:3: unknown instruction XOR, expected AND, OR or NOT"
+        );
+        assert_eq!(parse_error("NOT A\nWALK"), "1:6: NOT takes two registers");
+        assert_eq!(
+            parse_error("NOT A J T\nWALK"),
+            "1:9: NOT takes two registers"
+        );
+        assert_eq!(parse_error("OR X J\nWALK"), "1:4: unknown register X");
+        assert_eq!(
+            parse_error("OR A  D\nRUN"),
+            "1:7: cannot write to D, only T and J are writable"
+        );
+        assert_eq!(
+            parse_error("OR A J\nAND H J\nWALK"),
+            "2:5: H can only be read in RUN mode"
+        );
+        assert_eq!(parse_error("RUN J"), "1:5: RUN takes no registers");
+
+        let too_long = "OR A J\n".repeat(16) + "WALK";
+        assert_eq!(
+            parse_error(&too_long),
+            "16:1: scripts are limited to 15 instructions"
+        );
+        assert!(parse_script(&("OR A J\n".repeat(15) + "WALK")).is_ok());
     }
 }
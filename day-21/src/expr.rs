@@ -0,0 +1,567 @@
+//! Compiles boolean expressions over the droid's sensors, such as
+//! `!(A & B & C) & D`, into springscript. `!` binds tightest, then `&`,
+//! then `|`.
+//!
+//! Code is generated two ways and the shorter wins: straight from the
+//! expression as written, trying each operand order and De Morgan's dual
+//! at every node, and from a minimised sum of products of its truth table.
+//! The first keeps nesting like `!(A & B & C)` cheap, the second cleans up
+//! redundant expressions.
+
+use crate::droid::{Instruction, Mode, Register, Script, MAX_INSTRUCTIONS};
+use crate::logic::{compile, minimize, Cube};
+
+use std::fmt;
+
+const SENSORS: [Register; 9] = [
+    Register::A,
+    Register::B,
+    Register::C,
+    Register::D,
+    Register::E,
+    Register::F,
+    Register::G,
+    Register::H,
+    Register::I,
+];
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Expr {
+    Sensor(Register),
+    Not(Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+}
+
+impl Expr {
+    fn not(expr: Expr) -> Expr {
+        match expr {
+            Expr::Not(inner) => *inner,
+            other => Expr::Not(Box::new(other)),
+        }
+    }
+
+    fn and(items: Vec<Expr>) -> Expr {
+        let mut flat = Vec::new();
+        for item in items {
+            match item {
+                Expr::And(inner) => flat.extend(inner),
+                other => flat.push(other),
+            }
+        }
+        if flat.len() == 1 {
+            flat.pop().unwrap()
+        } else {
+            Expr::And(flat)
+        }
+    }
+
+    fn or(items: Vec<Expr>) -> Expr {
+        let mut flat = Vec::new();
+        for item in items {
+            match item {
+                Expr::Or(inner) => flat.extend(inner),
+                other => flat.push(other),
+            }
+        }
+        if flat.len() == 1 {
+            flat.pop().unwrap()
+        } else {
+            Expr::Or(flat)
+        }
+    }
+
+    /// The sum of products `cubes`, where bit `i` is `sensors[i]`.
+    fn from_cubes(cubes: &[Cube], sensors: &[Register]) -> Expr {
+        Expr::or(
+            cubes
+                .iter()
+                .map(|cube| {
+                    let literals = sensors
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _)| cube.care & 1 << i != 0)
+                        .map(|(i, &sensor)| {
+                            if cube.value & 1 << i != 0 {
+                                Expr::Sensor(sensor)
+                            } else {
+                                Expr::not(Expr::Sensor(sensor))
+                            }
+                        })
+                        .collect();
+                    Expr::and(literals)
+                })
+                .collect(),
+        )
+    }
+
+    /// De Morgan's dual, which is this expression with a `!` in front.
+    fn dual(&self) -> Expr {
+        match self {
+            Expr::And(items) => Expr::Or(items.iter().cloned().map(Expr::not).collect()),
+            Expr::Or(items) => Expr::And(items.iter().cloned().map(Expr::not).collect()),
+            other => Expr::not(other.clone()),
+        }
+    }
+
+    pub fn reads(&self, sensor: Register) -> bool {
+        match self {
+            Expr::Sensor(reg) => *reg == sensor,
+            Expr::Not(inner) => inner.reads(sensor),
+            Expr::And(items) | Expr::Or(items) => items.iter().any(|item| item.reads(sensor)),
+        }
+    }
+
+    pub fn eval<F: Fn(Register) -> bool>(&self, sensor: &F) -> bool {
+        match self {
+            Expr::Sensor(reg) => sensor(*reg),
+            Expr::Not(inner) => !inner.eval(sensor),
+            Expr::And(items) => items.iter().all(|item| item.eval(sensor)),
+            Expr::Or(items) => items.iter().any(|item| item.eval(sensor)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ExprError {
+    /// Columns count from 1.
+    Syntax {
+        column: usize,
+        message: String,
+    },
+    TooLong(usize),
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Syntax { column, message } => write!(f, "column {}: {}", column, message),
+            Self::TooLong(length) => write!(
+                f,
+                "needs {} instructions, scripts are limited to {}",
+                length, MAX_INSTRUCTIONS
+            ),
+        }
+    }
+}
+
+/// Splits an expression into sensor names and single-character symbols,
+/// along with the column each starts at.
+fn tokens(text: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        let mut end = start + c.len_utf8();
+        if c.is_alphanumeric() {
+            while let Some(&(i, next)) = chars.peek() {
+                if !next.is_alphanumeric() {
+                    break;
+                }
+                end = i + next.len_utf8();
+                chars.next();
+            }
+        }
+        tokens.push((text[..start].chars().count() + 1, &text[start..end]));
+    }
+    tokens
+}
+
+struct Parser<'a> {
+    tokens: Vec<(usize, &'a str)>,
+    pos: usize,
+    end: usize,
+    mode: Mode,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).map(|&(_, token)| token)
+    }
+
+    fn column(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.end, |&(column, _)| column)
+    }
+
+    fn error<T>(column: usize, message: String) -> Result<T, ExprError> {
+        Err(ExprError::Syntax { column, message })
+    }
+
+    fn or(&mut self) -> Result<Expr, ExprError> {
+        let mut items = vec![self.and()?];
+        while self.peek() == Some("|") {
+            self.pos += 1;
+            items.push(self.and()?);
+        }
+        Ok(Expr::or(items))
+    }
+
+    fn and(&mut self) -> Result<Expr, ExprError> {
+        let mut items = vec![self.unary()?];
+        while self.peek() == Some("&") {
+            self.pos += 1;
+            items.push(self.unary()?);
+        }
+        Ok(Expr::and(items))
+    }
+
+    fn unary(&mut self) -> Result<Expr, ExprError> {
+        let column = self.column();
+        let token = match self.peek() {
+            Some(token) => token,
+            None => {
+                return Self::error(
+                    column,
+                    "expected a sensor, found the end of the expression".to_string(),
+                )
+            }
+        };
+        self.pos += 1;
+
+        match token {
+            "!" => Ok(Expr::not(self.unary()?)),
+            "(" => {
+                let expr = self.or()?;
+                match self.peek() {
+                    Some(")") => {
+                        self.pos += 1;
+                        Ok(expr)
+                    }
+                    Some(other) => {
+                        Self::error(self.column(), format!("expected ), found {}", other))
+                    }
+                    None => Self::error(column, "unclosed (".to_string()),
+                }
+            }
+            _ => match Register::from_string(token) {
+                Some(reg) if reg.is_writable() => {
+                    Self::error(column, format!("{} is not a sensor", reg))
+                }
+                Some(reg) if self.mode == Mode::Walk && reg.is_run_only() => {
+                    Self::error(column, format!("{} can only be read in RUN mode", reg))
+                }
+                Some(reg) => Ok(Expr::Sensor(reg)),
+                None if token.chars().all(char::is_alphanumeric) => {
+                    Self::error(column, format!("unknown sensor {}", token))
+                }
+                None => Self::error(column, format!("expected a sensor, found {}", token)),
+            },
+        }
+    }
+}
+
+pub fn parse_expression(text: &str, mode: Mode) -> Result<Expr, ExprError> {
+    let mut parser = Parser {
+        tokens: tokens(text),
+        pos: 0,
+        end: text.chars().count() + 1,
+        mode,
+    };
+    let expr = parser.or()?;
+    match parser.peek() {
+        Some(token) => Parser::error(parser.column(), format!("unexpected {}", token)),
+        None => Ok(expr),
+    }
+}
+
+/// Which of `T` and `J` are still known to be false.
+#[derive(Debug, Clone, Copy)]
+struct Clean {
+    t: bool,
+    j: bool,
+}
+
+impl Clean {
+    fn is_clean(self, reg: Register) -> bool {
+        match reg {
+            Register::T => self.t,
+            _ => self.j,
+        }
+    }
+
+    fn after(mut self, code: &[Instruction]) -> Clean {
+        for ins in code {
+            match ins {
+                Instruction::And(_, Register::T)
+                | Instruction::Or(_, Register::T)
+                | Instruction::Not(_, Register::T) => self.t = false,
+                _ => self.j = false,
+            }
+        }
+        self
+    }
+}
+
+fn other(reg: Register) -> Register {
+    match reg {
+        Register::T => Register::J,
+        _ => Register::T,
+    }
+}
+
+fn shortest<I>(candidates: I) -> Option<Vec<Instruction>>
+where
+    I: IntoIterator<Item = Option<Vec<Instruction>>>,
+{
+    candidates
+        .into_iter()
+        .flatten()
+        .min_by_key(|code| code.len())
+}
+
+/// Generates code that leaves `expr` in `reg`, or `None` if it needs more
+/// registers than it has. With `scratch` set it may also clobber the other
+/// register. With `flip` set it also tries the negated dual of `expr`.
+fn generate(
+    expr: &Expr,
+    reg: Register,
+    clean: Clean,
+    scratch: bool,
+    flip: bool,
+) -> Option<Vec<Instruction>> {
+    let negate = |code: Option<Vec<Instruction>>, from| {
+        code.map(|mut code| {
+            code.push(Instruction::Not(from, reg));
+            code
+        })
+    };
+
+    match expr {
+        Expr::Sensor(sensor) => Some(if clean.is_clean(reg) {
+            vec![Instruction::Or(*sensor, reg)]
+        } else {
+            vec![Instruction::Not(*sensor, reg), Instruction::Not(reg, reg)]
+        }),
+        Expr::Not(inner) => {
+            if let Expr::Sensor(sensor) = **inner {
+                return Some(vec![Instruction::Not(sensor, reg)]);
+            }
+            let mut candidates = vec![negate(generate(inner, reg, clean, scratch, true), reg)];
+            if scratch {
+                let other = other(reg);
+                candidates.push(negate(generate(inner, other, clean, true, true), other));
+            }
+            shortest(candidates)
+        }
+        Expr::And(items) | Expr::Or(items) => {
+            let mut candidates = (0..items.len())
+                .map(|head| combine(expr, items, head, reg, clean, scratch))
+                .collect::<Vec<_>>();
+            if flip {
+                let dual = expr.dual();
+                candidates.push(negate(generate(&dual, reg, clean, scratch, false), reg));
+                if scratch {
+                    let other = other(reg);
+                    candidates.push(negate(generate(&dual, other, clean, true, false), other));
+                }
+            }
+            shortest(candidates)
+        }
+    }
+}
+
+/// Loads `items[head]` into `reg` and folds the other items into it.
+/// Sensors fold in directly, anything else goes through the other register.
+fn combine(
+    expr: &Expr,
+    items: &[Expr],
+    head: usize,
+    reg: Register,
+    clean: Clean,
+    scratch: bool,
+) -> Option<Vec<Instruction>> {
+    let fold = |from| match expr {
+        Expr::And(_) => Instruction::And(from, reg),
+        _ => Instruction::Or(from, reg),
+    };
+    let other = other(reg);
+
+    let mut code = generate(&items[head], reg, clean, scratch, true)?;
+    let mut rest = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        match item {
+            _ if i == head => (),
+            Expr::Sensor(sensor) => code.push(fold(*sensor)),
+            _ if scratch => rest.push(item),
+            _ => return None,
+        }
+    }
+
+    // Only the first item loaded into the other register can find it
+    // clean, so try each one there.
+    let clean = clean.after(&code);
+    let orders = (0..rest.len().max(1)).map(|first| {
+        let mut code = Vec::new();
+        let mut clean = clean;
+        let order = rest[first..].iter().chain(&rest[..first]);
+        for item in order {
+            let loaded = generate(item, other, clean, false, true)?;
+            clean = clean.after(&loaded);
+            code.extend(loaded);
+            code.push(fold(other));
+        }
+        Some(code)
+    });
+    let folded = shortest(orders)?;
+    code.extend(folded);
+    Some(code)
+}
+
+/// Compiles an expression into the shortest springscript this can find
+/// that leaves it in `J`.
+pub fn compile_expression(text: &str, mode: Mode) -> Result<Script, ExprError> {
+    let expr = parse_expression(text, mode)?;
+    let sensors = SENSORS
+        .iter()
+        .copied()
+        .filter(|&sensor| expr.reads(sensor))
+        .collect::<Vec<_>>();
+
+    let (on, off): (Vec<u16>, Vec<u16>) = (0..1u16 << sensors.len()).partition(|&reading| {
+        expr.eval(&|sensor| {
+            let i = sensors.iter().position(|&s| s == sensor).unwrap();
+            reading & 1 << i != 0
+        })
+    });
+    let terms = minimize(&on, &off, sensors.len());
+    let complement = minimize(&off, &on, sensors.len());
+
+    let start = Clean { t: true, j: true };
+    let candidates = vec![
+        generate(&expr, Register::J, start, true, true),
+        Some(compile(&terms, &sensors)),
+        generate(
+            &Expr::from_cubes(&terms, &sensors),
+            Register::J,
+            start,
+            true,
+            true,
+        ),
+        generate(
+            &Expr::not(Expr::from_cubes(&complement, &sensors)),
+            Register::J,
+            start,
+            true,
+            true,
+        ),
+    ];
+    let instructions = shortest(candidates).unwrap();
+
+    if instructions.len() > MAX_INSTRUCTIONS {
+        return Err(ExprError::TooLong(instructions.len()));
+    }
+    Ok(Script { instructions, mode })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::execute;
+
+    fn sensor(reg: Register) -> Expr {
+        Expr::Sensor(reg)
+    }
+
+    #[test]
+    fn test_parse_expression() {
+        let expr = parse_expression("A | B & !C", Mode::Walk).unwrap();
+        assert_eq!(
+            expr,
+            Expr::Or(vec![
+                sensor(Register::A),
+                Expr::And(vec![
+                    sensor(Register::B),
+                    Expr::Not(Box::new(sensor(Register::C)))
+                ])
+            ])
+        );
+        assert_eq!(
+            parse_expression("(A&B) & (C)", Mode::Walk).unwrap(),
+            Expr::And(vec![
+                sensor(Register::A),
+                sensor(Register::B),
+                sensor(Register::C)
+            ])
+        );
+        assert_eq!(
+            parse_expression("!!D", Mode::Walk).unwrap(),
+            sensor(Register::D)
+        );
+    }
+
+    fn parse_error(text: &str) -> String {
+        parse_expression(text, Mode::Walk).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_expression_errors() {
+        assert_eq!(
+            parse_error("A &"),
+            "column 4: expected a sensor, found the end of the expression"
+        );
+        assert_eq!(parse_error("A & T"), "column 5: T is not a sensor");
+        assert_eq!(parse_error("A | X"), "column 5: unknown sensor X");
+        assert_eq!(parse_error("A ^ B"), "column 3: unexpected ^");
+        assert_eq!(parse_error("A & (B | C"), "column 5: unclosed (");
+        assert_eq!(parse_error("(A B)"), "column 4: expected ), found B");
+        assert_eq!(parse_error("& A"), "column 1: expected a sensor, found &");
+        assert_eq!(
+            parse_error("D & H"),
+            "column 5: H can only be read in RUN mode"
+        );
+        assert!(parse_expression("D & H", Mode::Run).is_ok());
+    }
+
+    fn assert_compiles(text: &str, mode: Mode) -> usize {
+        let expr = parse_expression(text, mode).unwrap();
+        let script = compile_expression(text, mode).unwrap();
+        for reading in 0..1 << SENSORS.len() {
+            let mut sensors = [false; 9];
+            for (i, sensor) in sensors.iter_mut().enumerate() {
+                *sensor = reading & 1 << i != 0;
+            }
+            let expected = expr.eval(&|reg| {
+                let i = SENSORS.iter().position(|&s| s == reg).unwrap();
+                sensors[i]
+            });
+            assert_eq!(
+                execute(&script.instructions, sensors),
+                expected,
+                "{} on {:09b} compiled to\n{}",
+                text,
+                reading,
+                script
+            );
+        }
+        script.instructions.len()
+    }
+
+    #[test]
+    fn test_compile_expression() {
+        // One shorter than the hand-written part 1 script.
+        assert_eq!(assert_compiles("!(A & B & C) & D", Mode::Walk), 5);
+        // Two shorter than the hand-written part 2 script.
+        assert_eq!(assert_compiles("!(A & B & C) & D & (E | H)", Mode::Run), 8);
+        assert_eq!(assert_compiles("!A", Mode::Walk), 1);
+        assert_eq!(assert_compiles("A | !A", Mode::Walk), 1);
+        assert_eq!(assert_compiles("A & !A", Mode::Walk), 0);
+        // Redundant terms are minimised away.
+        assert_eq!(assert_compiles("A & B | A & !B | A & C", Mode::Walk), 1);
+        assert_compiles("!(A | B) & (C | !D) | !C & D", Mode::Walk);
+        assert_compiles("(A | !B) & (!C | D) & (E | !F) & (G | !H | I)", Mode::Run);
+    }
+
+    #[test]
+    fn test_too_long() {
+        let parity = "(A & !B | !A & B) & !(C & !D | !C & D) | \
+                      !(A & !B | !A & B) & (C & !D | !C & D)";
+        match compile_expression(parity, Mode::Walk) {
+            Err(ExprError::TooLong(length)) => assert!(length > MAX_INSTRUCTIONS),
+            other => panic!("Expected the parity of A-D to be too long, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use crate::droid::Mode;
+use crate::simulator::Hull;
+
+const FAILURE_HEADER: &str = "Didn't make it across:";
+
+/// One frame of the droid's death animation: three rows of air above the
+/// hull, with the droid drawn somewhere in them or in the hull itself.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Frame {
+    pub hull: Hull,
+    pub x: usize,
+    /// Rows count down from the top, so 3 means the droid is in the hull.
+    pub row: usize,
+}
+
+impl Frame {
+    /// `None` if the droid is missing or the hull row has a tile that is not
+    /// ground or a hole.
+    fn from_lines(lines: &[&str]) -> Option<Self> {
+        let (row, x) = lines
+            .iter()
+            .enumerate()
+            .find_map(|(row, line)| line.find('@').map(|x| (row, x)))?;
+        // The droid only shows up in the hull row once it is in a hole.
+        let hull = lines.last()?.replace('@', ".");
+        Some(Frame {
+            hull: Hull::from_string(&hull).ok()?,
+            x,
+            row,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Failure {
+    pub mode: Mode,
+    pub frames: Vec<Frame>,
+}
+
+impl Failure {
+    pub fn hull(&self) -> &Hull {
+        &self.frames[0].hull
+    }
+
+    /// Where the droid went into the hole.
+    pub fn fell_at(&self) -> usize {
+        self.frames.last().unwrap().x
+    }
+}
+
+/// Pulls the failing run out of the droid's output, or `None` if the droid
+/// made it across or a frame could not be read.
+pub fn parse_failure(output: &str) -> Option<Failure> {
+    let mode = if output.contains("Running...") {
+        Mode::Run
+    } else {
+        Mode::Walk
+    };
+    let start = output.find(FAILURE_HEADER)? + FAILURE_HEADER.len();
+
+    let lines = output[start..]
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>();
+    let frames = lines
+        .chunks(4)
+        .filter(|chunk| chunk.len() == 4)
+        .map(Frame::from_lines)
+        .collect::<Option<Vec<_>>>()?;
+
+    if frames.is_empty() {
+        None
+    } else {
+        Some(Failure { mode, frames })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = "Input instructions:
+
+Walking...
+
+
+Didn't make it across:
+
+.................
+.................
+@................
+#####..#.########
+
+.................
+.....@...........
+.................
+#####..#.########
+
+.................
+.................
+.................
+#####..#@########
+
+";
+
+    #[test]
+    fn test_parse_failure() {
+        let failure = parse_failure(OUTPUT).unwrap();
+        assert_eq!(failure.mode, Mode::Walk);
+        assert_eq!(failure.frames.len(), 3);
+        assert_eq!(failure.hull().to_string(), "#####..#.########");
+        assert_eq!(failure.frames[1].x, 5);
+        assert_eq!(failure.frames[1].row, 1);
+        assert_eq!(failure.frames[2].hull, failure.frames[0].hull);
+        assert_eq!(failure.fell_at(), 8);
+
+        let running = OUTPUT.replace("Walking...", "Running...");
+        assert_eq!(parse_failure(&running).unwrap().mode, Mode::Run);
+    }
+
+    #[test]
+    fn test_malformed_frame() {
+        let bad_hull = OUTPUT.replace("#####..#@", "#####..#?");
+        assert_eq!(parse_failure(&bad_hull), None);
+        let no_droid = OUTPUT.replace(".....@.....", "...........");
+        assert_eq!(parse_failure(&no_droid), None);
+    }
+
+    #[test]
+    fn test_success_has_no_failure() {
+        assert_eq!(parse_failure("Input instructions:\n\nWalking...\n\n"), None);
+    }
+}
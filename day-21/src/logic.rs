@@ -0,0 +1,284 @@
+//! Two-level logic minimisation over the droid's sensors, and code
+//! generation from the result. Sensor readings are bit sets where bit `i`
+//! is the `i`th sensor in whatever list the caller is working with.
+
+use crate::droid::{Instruction, Register};
+
+/// A product of literals. `care` has a bit for each sensor in the product
+/// and `value` says whether that sensor appears plain or negated.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Cube {
+    pub care: u16,
+    pub value: u16,
+}
+
+impl Cube {
+    pub fn contains(self, reading: u16) -> bool {
+        reading & self.care == self.value
+    }
+
+    pub fn literals(self) -> u32 {
+        self.care.count_ones()
+    }
+
+    fn without(self, bit: u16) -> Cube {
+        Cube {
+            care: self.care & !bit,
+            value: self.value & !bit,
+        }
+    }
+}
+
+fn bits(mask: u16) -> impl Iterator<Item = u16> {
+    (0..16).map(|i| 1 << i).filter(move |bit| mask & bit != 0)
+}
+
+/// Every maximal product over `vars` sensors that avoids all of `off`.
+fn prime_implicants(off: &[u16], vars: usize) -> Vec<Cube> {
+    let is_implicant = |cube: Cube| off.iter().all(|&reading| !cube.contains(reading));
+    let mut primes = Vec::new();
+    for care in 0..1u16 << vars {
+        // Walk every subset of `care` as the literal values.
+        let mut value = care;
+        loop {
+            let cube = Cube { care, value };
+            if is_implicant(cube) && bits(care).all(|bit| !is_implicant(cube.without(bit))) {
+                primes.push(cube);
+            }
+            if value == 0 {
+                break;
+            }
+            value = (value - 1) & care;
+        }
+    }
+    primes
+}
+
+fn term_cost(cube: Cube) -> u32 {
+    cube.literals() + 1
+}
+
+fn cover(
+    on: &[u16],
+    primes: &[Cube],
+    chosen: &mut Vec<Cube>,
+    cost: u32,
+    best: &mut Option<(u32, Vec<Cube>)>,
+) {
+    if let Some((best_cost, _)) = best {
+        if cost >= *best_cost {
+            return;
+        }
+    }
+    let uncovered = on
+        .iter()
+        .filter(|&&reading| !chosen.iter().any(|cube| cube.contains(reading)))
+        .copied()
+        .collect::<Vec<_>>();
+    // Branch on the reading with the fewest ways to cover it.
+    let pick = uncovered
+        .iter()
+        .map(|&reading| {
+            let options = primes
+                .iter()
+                .filter(|cube| cube.contains(reading))
+                .copied()
+                .collect::<Vec<_>>();
+            options
+        })
+        .min_by_key(|options| options.len());
+    match pick {
+        None => *best = Some((cost, chosen.clone())),
+        Some(options) => {
+            for cube in options {
+                chosen.push(cube);
+                cover(on, primes, chosen, cost + term_cost(cube), best);
+                chosen.pop();
+            }
+        }
+    }
+}
+
+/// Finds a cheapest sum of products that is true on every reading in `on`
+/// and false on every reading in `off`. Everything else is a don't-care.
+pub fn minimize(on: &[u16], off: &[u16], vars: usize) -> Vec<Cube> {
+    let primes = prime_implicants(off, vars)
+        .into_iter()
+        .filter(|cube| on.iter().any(|&reading| cube.contains(reading)))
+        .collect::<Vec<_>>();
+    let mut best = None;
+    cover(on, &primes, &mut Vec::new(), 0, &mut best);
+    let mut terms = best.map(|(_, terms)| terms).unwrap_or_default();
+    terms.sort();
+    terms
+}
+
+/// Loads a product into `reg`. When `reg` is known to be false a plain
+/// literal can be loaded with a single `OR`.
+fn load_term(cube: Cube, sensors: &[Register], reg: Register, is_false: bool) -> Vec<Instruction> {
+    let plain = bits(cube.care & cube.value)
+        .map(|bit| sensors[bit.trailing_zeros() as usize])
+        .collect::<Vec<_>>();
+    let negated = bits(cube.care & !cube.value)
+        .map(|bit| sensors[bit.trailing_zeros() as usize])
+        .collect::<Vec<_>>();
+    let load = |sensor| {
+        if is_false {
+            vec![Instruction::Or(sensor, reg)]
+        } else {
+            vec![Instruction::Not(sensor, reg), Instruction::Not(reg, reg)]
+        }
+    };
+
+    let mut code = match negated.len() {
+        0 => {
+            let mut code = load(plain[0]);
+            code.extend(
+                plain[1..]
+                    .iter()
+                    .map(|&sensor| Instruction::And(sensor, reg)),
+            );
+            return code;
+        }
+        1 => vec![Instruction::Not(negated[0], reg)],
+        // !X & !Y & .. is !(X | Y | ..).
+        _ => {
+            let mut code = load(negated[0]);
+            code.extend(
+                negated[1..]
+                    .iter()
+                    .map(|&sensor| Instruction::Or(sensor, reg)),
+            );
+            code.push(Instruction::Not(reg, reg));
+            code
+        }
+    };
+    code.extend(plain.iter().map(|&sensor| Instruction::And(sensor, reg)));
+    code
+}
+
+/// Generates springscript that leaves the sum of products in `J`, using
+/// `T` as scratch. Literals shared by every term are factored out and
+/// applied last.
+pub fn compile(terms: &[Cube], sensors: &[Register]) -> Vec<Instruction> {
+    if terms.is_empty() {
+        return Vec::new();
+    }
+    let common_care = terms.iter().fold(!0, |care, cube| {
+        care & cube.care & !(cube.value ^ terms[0].value)
+    });
+    let common = Cube {
+        care: common_care,
+        value: terms[0].value & common_care,
+    };
+    let mut rest = terms
+        .iter()
+        .map(|cube| {
+            let mut cube = *cube;
+            for bit in bits(common.care) {
+                cube = cube.without(bit);
+            }
+            cube
+        })
+        .collect::<Vec<_>>();
+    // The first term goes straight into the empty `J`, so start with the
+    // one that saves the most by it.
+    rest.sort_by_key(|cube| std::cmp::Reverse(cube.literals()));
+
+    let mut code = Vec::new();
+    if rest.iter().any(|cube| cube.care == 0) {
+        if common.care == 0 {
+            // Always jump. `T` is still false at this point.
+            return vec![Instruction::Not(Register::T, Register::J)];
+        }
+        code.extend(load_term(common, sensors, Register::J, true));
+        return code;
+    }
+    for (i, &cube) in rest.iter().enumerate() {
+        if i == 0 {
+            code.extend(load_term(cube, sensors, Register::J, true));
+        } else if cube.literals() == 1 && cube.value != 0 {
+            let sensor = sensors[cube.care.trailing_zeros() as usize];
+            code.push(Instruction::Or(sensor, Register::J));
+        } else {
+            code.extend(load_term(cube, sensors, Register::T, false));
+            code.push(Instruction::Or(Register::T, Register::J));
+        }
+    }
+    for bit in bits(common.care) {
+        let sensor = sensors[bit.trailing_zeros() as usize];
+        if common.value & bit != 0 {
+            code.push(Instruction::And(sensor, Register::J));
+        } else {
+            code.push(Instruction::Not(sensor, Register::T));
+            code.push(Instruction::And(Register::T, Register::J));
+        }
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SENSORS: &[Register] = &[Register::A, Register::B, Register::C, Register::D];
+
+    fn eval(code: &[Instruction], reading: u16) -> bool {
+        let mut t = false;
+        let mut j = false;
+        let read = |reg: Register, t: bool, j: bool| match reg {
+            Register::T => t,
+            Register::J => j,
+            sensor => {
+                let i = SENSORS.iter().position(|&s| s == sensor).unwrap();
+                reading & (1 << i) != 0
+            }
+        };
+        for &ins in code {
+            let (value, y) = match ins {
+                Instruction::And(x, y) => (read(x, t, j) && read(y, t, j), y),
+                Instruction::Or(x, y) => (read(x, t, j) || read(y, t, j), y),
+                Instruction::Not(x, y) => (!read(x, t, j), y),
+            };
+            match y {
+                Register::T => t = value,
+                _ => j = value,
+            }
+        }
+        j
+    }
+
+    #[test]
+    fn test_minimize_and_compile() {
+        // (!A | !B | !C) & D, the part 1 rule, as a full truth table.
+        let rule = |r: u16| r & 0b0111 != 0b0111 && r & 0b1000 != 0;
+        let (on, off): (Vec<u16>, Vec<u16>) = (0..16).partition(|&r| rule(r));
+        let terms = minimize(&on, &off, 4);
+        assert_eq!(terms.len(), 3);
+        assert!(terms.iter().all(|cube| cube.literals() == 2));
+
+        let code = compile(&terms, SENSORS);
+        for r in 0..16 {
+            assert_eq!(eval(&code, r), rule(r), "reading {:04b}", r);
+        }
+        assert_eq!(code.len(), 6);
+    }
+
+    #[test]
+    fn test_dont_cares() {
+        // Only two readings matter, so one literal is enough.
+        let terms = minimize(&[0b0001], &[0b0000], 4);
+        assert_eq!(
+            terms,
+            vec![Cube {
+                care: 0b0001,
+                value: 0b0001
+            }]
+        );
+        assert_eq!(
+            compile(&terms, SENSORS),
+            vec![Instruction::Or(Register::A, Register::J)]
+        );
+        assert_eq!(compile(&[], SENSORS), vec![]);
+    }
+}
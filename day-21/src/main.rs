@@ -1,12 +1,34 @@
 mod droid;
-mod intcode;
+mod expr;
+mod frames;
+mod logic;
+mod simulator;
+mod synth;
 
+use droid::{Mode, Script};
+use frames::{parse_failure, Failure};
 use intcode::{load_program, Computer};
+use simulator::Corpus;
 
-use std::fs::File;
-use std::io::Read;
+use std::env;
+use std::fs;
+use std::process;
+
+const HULLS_FILE: &str = "./hulls.txt";
 
 fn main() {
+    let args = env::args().collect::<Vec<_>>();
+    match args.get(1).map(String::as_str) {
+        Some("synth") => {
+            synthesize_scripts();
+            return;
+        }
+        Some("compile") => {
+            compile_expression(&args[2..]);
+            return;
+        }
+        _ => (),
+    }
     part1();
     part2();
 }
@@ -18,7 +40,7 @@ fn part1() {
     cpu.flush_output();
     load_springscript(&mut cpu, "./part1.springscript");
     cpu.run();
-    let result = cpu.flush_output();
+    let result = report_run(&mut cpu);
     println!("part 1: {:?}", result);
 }
 
@@ -29,13 +51,149 @@ fn part2() {
     cpu.flush_output();
     load_springscript(&mut cpu, "./part2.springscript");
     cpu.run();
-    let result = cpu.flush_output();
+    let result = report_run(&mut cpu);
     println!("part 2: {:?}", result);
 }
 
 fn load_springscript(cpu: &mut Computer, filename: &str) {
-    let mut file = File::open(filename).unwrap();
-    let mut contents = String::new();
-    file.read_to_string(&mut contents).unwrap();
-    cpu.send_message(&contents);
+    let contents = fs::read_to_string(filename).unwrap();
+    let script = match droid::parse_script(&contents) {
+        Ok(script) => script,
+        Err(error) => {
+            eprintln!("{}:{}", filename, error);
+            process::exit(1);
+        }
+    };
+
+    let corpus = load_corpus();
+    for (hull, x) in corpus.failures(&script) {
+        println!("{}: the droid falls in at {} on {}", filename, x, hull);
+    }
+
+    for line in script.to_string().lines() {
+        cpu.send_message(line);
+    }
+}
+
+fn load_corpus() -> Corpus {
+    let text = match fs::read_to_string(HULLS_FILE) {
+        Ok(text) => text,
+        Err(error) => {
+            eprintln!("{}: {}", HULLS_FILE, error);
+            process::exit(1);
+        }
+    };
+    match Corpus::from_string(&text) {
+        Ok(corpus) => corpus,
+        Err(error) => {
+            eprintln!("{}:{}", HULLS_FILE, error);
+            process::exit(1);
+        }
+    }
+}
+
+/// Prints the droid's output and returns the hull damage, if it made it.
+/// When it did not, the hull it fell into is saved to the corpus.
+fn report_run(cpu: &mut Computer) -> Option<i64> {
+    let (text, result) = read_output(cpu);
+    print!("{}", text);
+
+    if result.is_none() {
+        if let Some(failure) = parse_failure(&text) {
+            record_failure(&failure);
+        }
+    }
+    result
+}
+
+fn record_failure(failure: &Failure) {
+    let mut corpus = load_corpus();
+    if corpus.add(failure.mode, failure.hull().clone()) {
+        fs::write(HULLS_FILE, corpus.to_string()).unwrap();
+        println!(
+            "saved {} to {}, the droid fell in at {}",
+            failure.hull(),
+            HULLS_FILE,
+            failure.fell_at()
+        );
+    }
+}
+
+/// Splits the droid's output into its ASCII text and the hull damage.
+fn read_output(cpu: &mut Computer) -> (String, Option<i64>) {
+    let mut text = String::new();
+    let mut result = None;
+    while let Some(data) = cpu.get_output() {
+        match char::from_u32(data as u32) {
+            Some(c) if data < 128 => text.push(c),
+            _ => result = Some(data),
+        }
+    }
+    (text, result)
+}
+
+/// Runs a script on the real droid, quietly.
+fn run_droid(script: &Script) -> (String, Option<i64>) {
+    let mem = load_program("./springdroid.intcode");
+    let mut cpu = Computer::new(&mem);
+    cpu.run();
+    read_output(&mut cpu);
+    for line in script.to_string().lines() {
+        cpu.send_message(line);
+    }
+    cpu.run();
+    read_output(&mut cpu)
+}
+
+fn synthesize_scripts() {
+    let mut corpus = load_corpus();
+    for &mode in &[Mode::Walk, Mode::Run] {
+        let mut damage = None;
+        let script = synth::synthesize(mode, &mut corpus, |script| {
+            let (text, result) = run_droid(script);
+            damage = result;
+            result.map_or_else(
+                || {
+                    let failure = parse_failure(&text).expect("No result or failure frames");
+                    println!("{} falls in at {}", failure.hull(), failure.fell_at());
+                    Some(failure.hull().clone())
+                },
+                |_| None,
+            )
+        });
+        match script {
+            Some(script) => print!("{}hull damage: {:?}\n\n", script, damage),
+            None => println!(
+                "no {} script fits in {} instructions",
+                mode,
+                droid::MAX_INSTRUCTIONS
+            ),
+        }
+    }
+    fs::write(HULLS_FILE, corpus.to_string()).unwrap();
+}
+
+/// Prints the springscript for `[WALK|RUN] EXPRESSION`.
+fn compile_expression(args: &[String]) {
+    let (mode, text) = match args {
+        [text] => (Mode::Walk, text),
+        [mode, text] => match Mode::from_string(&mode.to_uppercase()) {
+            Some(mode) => (mode, text),
+            None => {
+                eprintln!("unknown mode {}, expected WALK or RUN", mode);
+                process::exit(1);
+            }
+        },
+        _ => {
+            eprintln!("usage: compile [WALK|RUN] EXPRESSION");
+            process::exit(1);
+        }
+    };
+    match expr::compile_expression(text, mode) {
+        Ok(script) => print!("{}", script),
+        Err(error) => {
+            eprintln!("{}: {}", text, error);
+            process::exit(1);
+        }
+    }
 }
@@ -0,0 +1,302 @@
+use crate::droid::{Instruction, Mode, Register, Script};
+
+use std::fmt;
+
+/// How far the droid travels in one jump.
+const JUMP_DISTANCE: usize = 4;
+
+const CORPUS_HEADER: &str = "\
+# Hull patterns the springdroid has fallen into. Each line is the mode the
+# pattern needs and the hull from the droid's starting tile: `walk`
+# patterns can be crossed with sensors A-D, `run` patterns need E-I too.
+";
+
+/// A strip of hull as the droid's frames show it: `#` is ground and `.` is
+/// a hole. The droid starts on the first tile, and everything past the end
+/// is ground.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Hull {
+    ground: Vec<bool>,
+}
+
+impl Hull {
+    pub fn from_string(text: &str) -> Result<Self, String> {
+        let ground = text
+            .trim()
+            .chars()
+            .map(|c| match c {
+                '#' => Ok(true),
+                '.' => Ok(false),
+                bad => Err(format!("unexpected hull tile {:?}", bad)),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Hull { ground })
+    }
+
+    pub fn len(&self) -> usize {
+        self.ground.len()
+    }
+
+    pub fn is_ground(&self, x: usize) -> bool {
+        self.ground.get(x).copied().unwrap_or(true)
+    }
+}
+
+impl fmt::Display for Hull {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for &ground in &self.ground {
+            write!(f, "{}", if ground { '#' } else { '.' })?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Outcome {
+    Survived,
+    /// The droid stepped or landed on the hole at this position.
+    Fell(usize),
+}
+
+struct Registers {
+    t: bool,
+    j: bool,
+    sensors: [bool; 9],
+}
+
+impl Registers {
+    fn read(&self, register: Register) -> bool {
+        match register {
+            Register::T => self.t,
+            Register::J => self.j,
+            Register::A => self.sensors[0],
+            Register::B => self.sensors[1],
+            Register::C => self.sensors[2],
+            Register::D => self.sensors[3],
+            Register::E => self.sensors[4],
+            Register::F => self.sensors[5],
+            Register::G => self.sensors[6],
+            Register::H => self.sensors[7],
+            Register::I => self.sensors[8],
+        }
+    }
+
+    fn write(&mut self, register: Register, data: bool) {
+        match register {
+            Register::T => self.t = data,
+            Register::J => self.j = data,
+            other => panic!("Cannot write to {}", other),
+        }
+    }
+}
+
+/// Decides whether the droid standing at `x` jumps. `T` and `J` start out
+/// false on every decision, like on the real droid.
+pub fn decide(script: &Script, hull: &Hull, x: usize) -> bool {
+    let sensor_count = match script.mode {
+        Mode::Walk => 4,
+        Mode::Run => 9,
+    };
+    let mut sensors = [false; 9];
+    for (i, sensor) in sensors.iter_mut().enumerate().take(sensor_count) {
+        *sensor = hull.is_ground(x + i + 1);
+    }
+    execute(&script.instructions, sensors)
+}
+
+/// Runs springscript against one set of readings for sensors A to I and
+/// returns `J`.
+pub fn execute(instructions: &[Instruction], sensors: [bool; 9]) -> bool {
+    let mut regs = Registers {
+        t: false,
+        j: false,
+        sensors,
+    };
+
+    for &ins in instructions {
+        match ins {
+            Instruction::And(x, y) => regs.write(y, regs.read(x) && regs.read(y)),
+            Instruction::Or(x, y) => regs.write(y, regs.read(x) || regs.read(y)),
+            Instruction::Not(x, y) => regs.write(y, !regs.read(x)),
+        }
+    }
+    regs.j
+}
+
+/// Walks the droid across `hull` until it reaches the end or falls in.
+pub fn simulate(script: &Script, hull: &Hull) -> Outcome {
+    let mut x = 0;
+    while x < hull.len() {
+        x += if decide(script, hull, x) {
+            JUMP_DISTANCE
+        } else {
+            1
+        };
+        if !hull.is_ground(x) {
+            return Outcome::Fell(x);
+        }
+    }
+    Outcome::Survived
+}
+
+/// Hull patterns a script has to get across. Patterns marked `walk` can be
+/// crossed with sensors A to D, so every script is held to them, while
+/// `run` patterns only apply to `RUN` scripts.
+pub struct Corpus {
+    hulls: Vec<(Mode, Hull)>,
+}
+
+impl Corpus {
+    /// Errors start with the line number they were found on.
+    pub fn from_string(text: &str) -> Result<Self, String> {
+        let hulls = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(i, line)| {
+                let mut parts = line.split_whitespace();
+                let mode = match parts.next() {
+                    Some("walk") => Mode::Walk,
+                    Some("run") => Mode::Run,
+                    _ => return Err(format!("{}: expected walk or run", i)),
+                };
+                let hull = parts
+                    .next()
+                    .ok_or_else(|| format!("{}: missing hull pattern", i))?;
+                let hull = Hull::from_string(hull).map_err(|error| format!("{}: {}", i, error))?;
+                Ok((mode, hull))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Corpus { hulls })
+    }
+
+    /// Adds a pattern unless it is already known, returning whether it was
+    /// new.
+    pub fn add(&mut self, mode: Mode, hull: Hull) -> bool {
+        if self.hulls.iter().any(|(_, known)| *known == hull) {
+            return false;
+        }
+        self.hulls.push((mode, hull));
+        true
+    }
+
+    pub fn hulls(&self, mode: Mode) -> impl Iterator<Item = &Hull> {
+        self.hulls
+            .iter()
+            .filter(move |(hull_mode, _)| mode == Mode::Run || *hull_mode == Mode::Walk)
+            .map(|(_, hull)| hull)
+    }
+
+    /// Every pattern the script falls into, along with where.
+    pub fn failures<'a>(&'a self, script: &'a Script) -> impl Iterator<Item = (&'a Hull, usize)> {
+        self.hulls(script.mode)
+            .filter_map(move |hull| match simulate(script, hull) {
+                Outcome::Survived => None,
+                Outcome::Fell(x) => Some((hull, x)),
+            })
+    }
+}
+
+impl fmt::Display for Corpus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", CORPUS_HEADER)?;
+        for (mode, hull) in &self.hulls {
+            let mode = match mode {
+                Mode::Walk => "walk",
+                Mode::Run => "run",
+            };
+            writeln!(f, "{} {}", mode, hull)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::droid::parse_script;
+
+    fn corpus() -> Corpus {
+        Corpus::from_string(include_str!("../hulls.txt")).unwrap()
+    }
+
+    fn parse_hull(text: &str) -> Hull {
+        Hull::from_string(text).unwrap()
+    }
+
+    #[test]
+    fn test_simulate() {
+        let hull = parse_hull("#####.#..########");
+        let script = parse_script("NOT A J\nWALK").unwrap();
+        assert_eq!(simulate(&script, &hull), Outcome::Fell(8));
+        let script = parse_script("NOT C J\nAND D J\nWALK").unwrap();
+        assert_eq!(simulate(&script, &hull), Outcome::Fell(7));
+
+        // An empty script never jumps.
+        let script = parse_script("WALK").unwrap();
+        assert_eq!(simulate(&script, &hull), Outcome::Fell(5));
+        assert_eq!(simulate(&script, &parse_hull("#####")), Outcome::Survived);
+    }
+
+    #[test]
+    fn test_run_sensors() {
+        let script = parse_script("OR H J\nRUN").unwrap();
+        assert!(decide(&script, &parse_hull("#########."), 0));
+        assert!(!decide(&script, &parse_hull("########.#"), 0));
+        assert_eq!(
+            parse_hull("#####.#.#..##.###").to_string(),
+            "#####.#.#..##.###"
+        );
+    }
+
+    #[test]
+    fn test_solutions_survive_corpus() {
+        let corpus = corpus();
+        let part1 = parse_script(include_str!("../part1.springscript")).unwrap();
+        assert_eq!(corpus.failures(&part1).count(), 0);
+        let part2 = parse_script(include_str!("../part2.springscript")).unwrap();
+        assert_eq!(corpus.failures(&part2).count(), 0);
+
+        // The walking script is not enough once the run patterns count.
+        let part1_running = Script {
+            instructions: part1.instructions,
+            mode: Mode::Run,
+        };
+        assert!(corpus.failures(&part1_running).count() > 0);
+    }
+
+    #[test]
+    fn test_corpus_round_trip() {
+        let text = include_str!("../hulls.txt");
+        let mut corpus = Corpus::from_string(text).unwrap();
+        assert_eq!(corpus.to_string(), text);
+
+        assert!(!corpus.add(Mode::Run, parse_hull("#####.###########")));
+        assert!(corpus.add(Mode::Run, parse_hull("#####.##.########")));
+        assert_eq!(
+            corpus.to_string().lines().last(),
+            Some("run #####.##.########")
+        );
+    }
+
+    #[test]
+    fn test_bad_corpus() {
+        assert_eq!(
+            Hull::from_string("##@#").err(),
+            Some("unexpected hull tile '@'".to_string())
+        );
+        let errors = ["fly ####", "walk", "run ##x#"]
+            .iter()
+            .map(|line| Corpus::from_string(&format!("# hulls\nwalk ####\n{}\n", line)).err())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec![
+                Some("3: expected walk or run".to_string()),
+                Some("3: missing hull pattern".to_string()),
+                Some("3: unexpected hull tile 'x'".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,215 @@
+//! Searches for springscript that gets the droid across a set of hulls.
+//!
+//! The search works in two stages. First it looks for jump policies: a
+//! choice of jump or walk for each sensor reading the droid runs into, such
+//! that it crosses every hull. Readings it never sees are left open. Then
+//! each policy is minimised into a sum of products over the sensors and
+//! compiled, and the shortest script wins. Enumerating scripts directly
+//! does not scale past five or six instructions with nine sensors.
+
+use crate::droid::{Instruction, Mode, Register, Script, MAX_INSTRUCTIONS};
+use crate::logic::{compile, minimize};
+use crate::simulator::{simulate, Corpus, Hull, Outcome};
+
+use std::collections::HashMap;
+
+const WALK_SENSORS: &[Register] = &[Register::A, Register::B, Register::C, Register::D];
+const RUN_SENSORS: &[Register] = &[
+    Register::A,
+    Register::B,
+    Register::C,
+    Register::D,
+    Register::E,
+    Register::F,
+    Register::G,
+    Register::H,
+    Register::I,
+];
+
+/// How many policies to minimise before settling for the best so far.
+const POLICY_LIMIT: usize = 2000;
+
+/// The sensor readings the droid can see on a set of hulls, numbered in
+/// order of appearance. Each reading is a bit set over the sensors.
+struct Readings {
+    hulls: Vec<(Hull, Vec<usize>)>,
+    readings: Vec<u16>,
+}
+
+impl Readings {
+    fn new(sensors: &[Register], hulls: &[Hull]) -> Self {
+        let mut index = HashMap::new();
+        let mut readings = Vec::new();
+        let hulls = hulls
+            .iter()
+            .map(|hull| {
+                let positions = (0..hull.len())
+                    .map(|x| {
+                        let reading = (0..sensors.len())
+                            .filter(|i| hull.is_ground(x + i + 1))
+                            .fold(0, |reading, i| reading | 1 << i);
+                        *index.entry(reading).or_insert_with(|| {
+                            readings.push(reading);
+                            readings.len() - 1
+                        })
+                    })
+                    .collect();
+                (hull.clone(), positions)
+            })
+            .collect::<Vec<_>>();
+        Readings { hulls, readings }
+    }
+}
+
+/// Depth-first search over policies, calling `found` with each complete
+/// one until it returns false. Walking is tried before jumping.
+fn policies<F>(
+    readings: &Readings,
+    policy: &mut Vec<Option<bool>>,
+    hull: usize,
+    found: &mut F,
+) -> bool
+where
+    F: FnMut(&[Option<bool>]) -> bool,
+{
+    let (ground, positions) = match readings.hulls.get(hull) {
+        Some(entry) => entry,
+        None => return found(policy),
+    };
+
+    // Follow the hull as far as the policy already decides.
+    let mut x = 0;
+    while x < ground.len() {
+        let jump = match policy[positions[x]] {
+            Some(jump) => jump,
+            None => break,
+        };
+        x += if jump { 4 } else { 1 };
+        if !ground.is_ground(x) {
+            return true;
+        }
+    }
+    if x >= ground.len() {
+        return policies(readings, policy, hull + 1, found);
+    }
+
+    let reading = positions[x];
+    for &jump in &[false, true] {
+        if ground.is_ground(x + if jump { 4 } else { 1 }) {
+            policy[reading] = Some(jump);
+            let keep_going = policies(readings, policy, hull, found);
+            policy[reading] = None;
+            if !keep_going {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Finds a short script that crosses every hull, or `None` if no policy
+/// compiles to one that fits in the droid's memory.
+pub fn search(mode: Mode, hulls: &[Hull]) -> Option<Script> {
+    let sensors = match mode {
+        Mode::Walk => WALK_SENSORS,
+        Mode::Run => RUN_SENSORS,
+    };
+    let readings = Readings::new(sensors, hulls);
+
+    let mut best: Option<Vec<Instruction>> = None;
+    let mut tried = 0;
+    let mut policy = vec![None; readings.readings.len()];
+    policies(&readings, &mut policy, 0, &mut |policy| {
+        let pick = |jump| {
+            policy
+                .iter()
+                .zip(&readings.readings)
+                .filter(|(&decision, _)| decision == Some(jump))
+                .map(|(_, &reading)| reading)
+                .collect::<Vec<_>>()
+        };
+        let code = compile(&minimize(&pick(true), &pick(false), sensors.len()), sensors);
+        if best.as_ref().is_none_or(|best| code.len() < best.len()) {
+            best = Some(code);
+        }
+        tried += 1;
+        tried < POLICY_LIMIT
+    });
+
+    best.filter(|code| code.len() <= MAX_INSTRUCTIONS)
+        .map(|instructions| Script { instructions, mode })
+        .filter(|script| {
+            hulls
+                .iter()
+                .all(|hull| simulate(script, hull) == Outcome::Survived)
+        })
+}
+
+/// Alternates between searching against the corpus and trying the result
+/// on the real droid. `oracle` runs a script and returns the hull the droid
+/// fell into, if any, which joins the corpus for the next search. Returns
+/// `None` if no script fits in the droid's memory.
+pub fn synthesize<F>(mode: Mode, corpus: &mut Corpus, mut oracle: F) -> Option<Script>
+where
+    F: FnMut(&Script) -> Option<Hull>,
+{
+    loop {
+        let hulls = corpus.hulls(mode).cloned().collect::<Vec<_>>();
+        let script = search(mode, &hulls)?;
+        match oracle(&script) {
+            None => return Some(script),
+            Some(hull) => {
+                if !corpus.add(mode, hull.clone()) {
+                    panic!(
+                        "The droid fell into known hull {} running\n{}",
+                        hull, script
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_hulls() -> Corpus {
+        Corpus::from_string(include_str!("../hulls.txt")).unwrap()
+    }
+
+    #[test]
+    fn test_search_walk() {
+        let corpus = all_hulls();
+        let hulls = corpus.hulls(Mode::Walk).cloned().collect::<Vec<_>>();
+        let script = search(Mode::Walk, &hulls).unwrap();
+        for hull in &hulls {
+            assert_eq!(simulate(&script, hull), Outcome::Survived);
+        }
+        // The hand-written part 1 script takes six.
+        assert!(script.instructions.len() <= 6);
+    }
+
+    #[test]
+    fn test_search_gives_up() {
+        // A hole four wide is too far to jump.
+        let hulls = vec![Hull::from_string("#....#").unwrap()];
+        assert_eq!(search(Mode::Run, &hulls), None);
+    }
+
+    #[test]
+    fn test_synthesize_converges() {
+        let hidden = all_hulls();
+        let mut corpus = Corpus::from_string("walk #####.###########").unwrap();
+        let mut queries = 0;
+        let script = synthesize(Mode::Run, &mut corpus, |script| {
+            queries += 1;
+            hidden.failures(script).next().map(|(hull, _)| hull.clone())
+        })
+        .unwrap();
+
+        assert_eq!(hidden.failures(&script).count(), 0);
+        assert!(queries > 1);
+        assert!(corpus.hulls(Mode::Run).count() > 1);
+    }
+}
@@ -0,0 +1,146 @@
+//! Generates the C header for `src/ffi.rs`. The header lands in `OUT_DIR`,
+//! and a test in `ffi.rs` checks that `include/intcode.h` matches it.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const PREAMBLE: &str = "/* Generated from src/ffi.rs by build.rs. Do not edit by hand. */
+
+#ifndef INTCODE_H
+#define INTCODE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern \"C\" {
+#endif
+
+typedef struct intcode_vm intcode_vm;
+typedef struct intcode_snapshot intcode_snapshot;
+";
+
+const POSTAMBLE: &str = "
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* INTCODE_H */
+";
+
+fn c_type(rust: &str) -> String {
+    let rust = rust.trim();
+    let (prefix, inner) = if let Some(inner) = rust.strip_prefix("*const ") {
+        ("const ", Some(inner))
+    } else if let Some(inner) = rust.strip_prefix("*mut ") {
+        ("", Some(inner))
+    } else {
+        ("", None)
+    };
+    let base = |name: &str| match name {
+        "i32" => "int32_t",
+        "i64" => "int64_t",
+        "usize" => "size_t",
+        "IntcodeVm" => "intcode_vm",
+        "IntcodeSnapshot" => "intcode_snapshot",
+        other => panic!("No C type for {}", other),
+    };
+    match inner {
+        Some(inner) => format!("{}{} *", prefix, base(inner)),
+        None => format!("{} ", base(rust)),
+    }
+}
+
+fn c_function(signature: &str) -> String {
+    let after_fn = &signature[signature.find("fn ").expect("Missing fn") + 3..];
+    let open = after_fn.find('(').expect("Missing (");
+    let close = after_fn.rfind(')').expect("Missing )");
+    let name = &after_fn[..open];
+
+    let params = after_fn[open + 1..close]
+        .split(',')
+        .map(|param| param.trim())
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let mut parts = param.splitn(2, ':');
+            let param_name = parts.next().unwrap().trim();
+            let param_type = c_type(parts.next().expect("Missing parameter type"));
+            format!("{}{}", param_type, param_name)
+        })
+        .collect::<Vec<_>>();
+
+    let ret = match after_fn[close + 1..].trim().trim_end_matches('{').trim() {
+        "" => "void ".to_string(),
+        ret => c_type(ret.trim_start_matches("->")),
+    };
+
+    let params = if params.is_empty() {
+        "void".to_string()
+    } else {
+        params.join(", ")
+    };
+    format!("{}{}({});", ret, name, params)
+}
+
+fn generate(source: &str) -> String {
+    let mut header = PREAMBLE.to_string();
+    let mut docs = Vec::new();
+    let mut signature: Option<String> = None;
+
+    for line in source.lines() {
+        let line = line.trim();
+        if line.starts_with("#[cfg(test)]") {
+            break;
+        }
+
+        if let Some(partial) = signature.as_mut() {
+            partial.push(' ');
+            partial.push_str(line);
+            if line.ends_with('{') {
+                header.push('\n');
+                header.push_str(&docs.join(""));
+                header.push_str(&c_function(partial));
+                header.push('\n');
+                docs.clear();
+                signature = None;
+            }
+        } else if let Some(doc) = line.strip_prefix("///") {
+            docs.push(format!("/*{} */\n", doc));
+        } else if let Some(constant) = line.strip_prefix("pub const ") {
+            let name = constant.split(':').next().unwrap();
+            let value = line.split('=').nth(1).unwrap().trim().trim_end_matches(';');
+            let value = if value.starts_with('-') {
+                format!("({})", value)
+            } else {
+                value.to_string()
+            };
+            header.push_str(&docs.join(""));
+            header.push_str(&format!("#define {} {}\n", name, value));
+            docs.clear();
+        } else if line.contains("extern \"C\" fn ") {
+            if line.ends_with('{') {
+                header.push('\n');
+                header.push_str(&docs.join(""));
+                header.push_str(&c_function(line));
+                header.push('\n');
+                docs.clear();
+            } else {
+                signature = Some(line.to_string());
+            }
+        } else if !line.starts_with("#[") {
+            docs.clear();
+        }
+    }
+
+    header.push_str(POSTAMBLE);
+    header
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    let source = fs::read_to_string("src/ffi.rs").expect("Failed to read src/ffi.rs");
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR is not set");
+    fs::write(Path::new(&out_dir).join("intcode.h"), generate(&source))
+        .expect("Failed to write intcode.h");
+}
@@ -0,0 +1,69 @@
+/* Generated from src/ffi.rs by build.rs. Do not edit by hand. */
+
+#ifndef INTCODE_H
+#define INTCODE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct intcode_vm intcode_vm;
+typedef struct intcode_snapshot intcode_snapshot;
+/* The computer can keep running. */
+#define INTCODE_RUNNING 0
+/* At least one output value is waiting to be popped. */
+#define INTCODE_OUTPUT 1
+/* The computer is waiting for input. */
+#define INTCODE_BLOCKED 2
+#define INTCODE_HALTED 3
+/* The computer stopped on an error such as an overflow or an unknown op */
+/* code. */
+#define INTCODE_ERROR 4
+/* A null handle, or an address outside memory. */
+#define INTCODE_BAD_ARGUMENT (-1)
+
+/* Creates a computer from `len` words of program memory. Free it with */
+/* `intcode_free`. */
+intcode_vm *intcode_new(const int64_t *memory, size_t len);
+
+void intcode_free(intcode_vm *vm);
+
+int32_t intcode_push_input(intcode_vm *vm, int64_t data);
+
+/* Pops the oldest output value into `data`. Returns 1 if there was one and */
+/* 0 if the queue was empty. */
+int32_t intcode_pop_output(intcode_vm *vm, int64_t *data);
+
+/* Executes one instruction and returns the resulting status. */
+int32_t intcode_step(intcode_vm *vm);
+
+/* Runs until there is output to pop, the computer needs input, halts, or */
+/* fails. Never returns `INTCODE_RUNNING`. */
+int32_t intcode_run(intcode_vm *vm);
+
+int32_t intcode_status(const intcode_vm *vm);
+
+/* Returns the program counter, or -1 for a null handle. */
+int64_t intcode_pc(const intcode_vm *vm);
+
+int32_t intcode_read_memory(const intcode_vm *vm, size_t addr, int64_t *data);
+
+int32_t intcode_write_memory(intcode_vm *vm, size_t addr, int64_t data);
+
+/* Copies the computer's whole state. Free it with `intcode_snapshot_free`. */
+intcode_snapshot *intcode_take_snapshot(const intcode_vm *vm);
+
+/* Puts the computer back in the snapshot's state. The snapshot stays valid */
+/* and can be restored again, which also recovers a computer that failed. */
+int32_t intcode_restore(intcode_vm *vm, const intcode_snapshot *snapshot);
+
+void intcode_snapshot_free(intcode_snapshot *snapshot);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* INTCODE_H */
@@ -0,0 +1,117 @@
+//! Runs a `Computer` on its own thread, talking to it over channels.
+
+use crate::computer::{Computer, Memory};
+
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::{thread, time};
+
+pub trait IntcodeComputer {
+    fn start(&mut self);
+    fn is_running(&self) -> bool;
+    fn stop(&mut self);
+    fn send_input(&mut self, data: i64);
+    fn recv_output(&mut self) -> Option<i64>;
+}
+
+pub struct AsyncComputer {
+    program: Memory,
+    input_tx: Option<Sender<i64>>,
+    output_rx: Option<Receiver<i64>>,
+    handle: Option<thread::JoinHandle<()>>,
+    halted_rx: Option<Receiver<()>>,
+}
+
+impl AsyncComputer {
+    pub fn new(program: &Memory) -> Self {
+        AsyncComputer {
+            program: program.clone(),
+            input_tx: None,
+            output_rx: None,
+            handle: None,
+            halted_rx: None,
+        }
+    }
+
+    pub fn try_recv_output(&mut self) -> Option<i64> {
+        self.output_rx.as_ref().unwrap().try_recv().ok()
+    }
+}
+
+impl IntcodeComputer for AsyncComputer {
+    fn is_running(&self) -> bool {
+        match (&self.handle, &self.halted_rx) {
+            (Some(_), Some(halted_rx)) => halted_rx.try_recv().is_err(),
+            _ => true,
+        }
+    }
+
+    fn start(&mut self) {
+        let (input_tx, input_rx) = channel::<i64>();
+        let (output_tx, output_rx) = channel::<i64>();
+        let (halted_tx, halted_rx) = channel::<()>();
+
+        self.input_tx = Some(input_tx);
+        self.output_rx = Some(output_rx);
+        self.halted_rx = Some(halted_rx);
+
+        let mut cpu = Computer::new(&self.program);
+        let handle = thread::spawn(move || {
+            loop {
+                if let Ok(data) = input_rx.try_recv() {
+                    cpu.set_input(data);
+                }
+
+                cpu.step();
+
+                if let Some(data) = cpu.get_output() {
+                    output_tx.send(data).unwrap();
+                }
+
+                // A computer with an error never steps again, so it is as
+                // good as halted.
+                if cpu.is_halted() || cpu.get_error().is_some() {
+                    break;
+                }
+
+                if cpu.is_blocked() {
+                    thread::sleep(time::Duration::from_millis(1));
+                }
+            }
+
+            halted_tx.send(()).unwrap();
+        });
+        self.handle = Some(handle);
+    }
+
+    fn send_input(&mut self, data: i64) {
+        self.input_tx.as_ref().unwrap().send(data).ok();
+    }
+
+    fn recv_output(&mut self) -> Option<i64> {
+        self.output_rx.as_ref().unwrap().recv().ok()
+    }
+
+    fn stop(&mut self) {
+        self.handle.take().unwrap().join().unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_echo() {
+        // Echoes inputs until it reads a zero.
+        let mut cpu = AsyncComputer::new(&vec![3, 11, 1005, 11, 6, 99, 4, 11, 1105, 1, 0, 0]);
+        cpu.start();
+        for data in 1..4 {
+            cpu.send_input(data);
+            assert_eq!(cpu.recv_output(), Some(data));
+        }
+        cpu.send_input(0);
+        assert_eq!(cpu.recv_output(), None);
+        cpu.stop();
+        assert_eq!(cpu.try_recv_output(), None);
+    }
+}
@@ -0,0 +1,320 @@
+//! The other Intcode interpreters in this repo, wrapped behind one interface so
+//! the fuzzer can run the same program on all of them. The day-02, day-05 and
+//! day-07 computers come from their day crates, so any fix made there is what
+//! gets tested here. They are only built with the `fuzz` feature. Every later
+//! day runs the shared `Computer`.
+
+use crate::computer::Computer;
+use crate::decoded::{Closures, DecodeOnce, Engine, Interpreter};
+
+#[cfg(feature = "fuzz")]
+use std::convert::TryFrom;
+use std::panic::{self, AssertUnwindSafe};
+
+/// The subset of Intcode a program uses. Each backend only runs programs
+/// from profiles it understands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// Add, multiply and halt with position-mode parameters only.
+    Day2,
+    /// Everything up to day 5: modes, input, output, jumps and comparisons.
+    Day5,
+}
+
+/// How a run ended. Memory is the program's own cells only, since some
+/// interpreters pad it with zeros.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Halted {
+        memory: Vec<i64>,
+        last_output: Option<i64>,
+    },
+    Crashed(String),
+    TimedOut,
+}
+
+pub trait Backend {
+    fn name(&self) -> &'static str;
+
+    fn supports(&self, profile: Profile) -> bool;
+
+    /// Runs `program` for at most `max_steps` instructions. Every input
+    /// instruction reads `input`.
+    fn run(&self, program: &[i64], input: i64, max_steps: usize) -> Outcome;
+}
+
+pub fn all_backends() -> Vec<Box<dyn Backend>> {
+    vec![
+        #[cfg(feature = "fuzz")]
+        Box::new(Day02Backend),
+        #[cfg(feature = "fuzz")]
+        Box::new(Day05Backend),
+        #[cfg(feature = "fuzz")]
+        Box::new(Day07Backend),
+        Box::new(ComputerBackend),
+        Box::new(DecodedBackend),
+        Box::new(ThreadedBackend),
+    ]
+}
+
+fn catch_crash<F>(run: F) -> Outcome
+where
+    F: FnOnce() -> Outcome,
+{
+    catch_panic(run).unwrap_or_else(Outcome::Crashed)
+}
+
+/// Runs `run`, turning a panic into its message.
+pub(crate) fn catch_panic<T, F>(run: F) -> Result<T, String>
+where
+    F: FnOnce() -> T,
+{
+    panic::catch_unwind(AssertUnwindSafe(run)).map_err(|payload| {
+        if let Some(message) = payload.downcast_ref::<&str>() {
+            message.to_string()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "unknown panic".to_string()
+        }
+    })
+}
+
+/// Day 2's `usize` computer. It has no way to loop, so it runs without a
+/// step limit.
+#[cfg(feature = "fuzz")]
+pub struct Day02Backend;
+
+#[cfg(feature = "fuzz")]
+impl Backend for Day02Backend {
+    fn name(&self) -> &'static str {
+        "day-02 usize"
+    }
+
+    fn supports(&self, profile: Profile) -> bool {
+        profile == Profile::Day2
+    }
+
+    fn run(&self, program: &[i64], _input: i64, _max_steps: usize) -> Outcome {
+        let memory = match program
+            .iter()
+            .map(|&data| usize::try_from(data))
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(memory) => memory,
+            Err(_) => return Outcome::Crashed("negative value in memory".to_string()),
+        };
+
+        catch_crash(|| {
+            let mut cpu = day02::Computer::new(memory);
+            match cpu.try_run() {
+                Ok(()) => Outcome::Halted {
+                    memory: cpu.memory().iter().map(|&data| data as i64).collect(),
+                    last_output: None,
+                },
+                Err(message) => Outcome::Crashed(message),
+            }
+        })
+    }
+}
+
+/// Day 5's `i32` computer, which keeps a single input value and only the
+/// most recent output.
+#[cfg(feature = "fuzz")]
+pub struct Day05Backend;
+
+#[cfg(feature = "fuzz")]
+impl Backend for Day05Backend {
+    fn name(&self) -> &'static str {
+        "day-05 i32"
+    }
+
+    fn supports(&self, _profile: Profile) -> bool {
+        true
+    }
+
+    fn run(&self, program: &[i64], input: i64, max_steps: usize) -> Outcome {
+        let memory = match program
+            .iter()
+            .map(|&data| i32::try_from(data))
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(memory) => memory,
+            Err(_) => return Outcome::Crashed("value does not fit in i32".to_string()),
+        };
+        let input = match i32::try_from(input) {
+            Ok(input) => input,
+            Err(_) => return Outcome::Crashed("input does not fit in i32".to_string()),
+        };
+
+        catch_crash(|| {
+            let mut cpu = day05::Computer::new(memory);
+            cpu.set_input(input);
+            for _ in 0..max_steps {
+                if cpu.is_halted() {
+                    return Outcome::Halted {
+                        memory: cpu.get_memory().iter().map(|&data| data as i64).collect(),
+                        last_output: cpu.get_output().map(|data| data as i64),
+                    };
+                }
+                cpu.step();
+            }
+            Outcome::TimedOut
+        })
+    }
+}
+
+/// Day 7's `i32` computer, which queues its input so amplifiers can be
+/// chained. The input value is pushed once for every step the program could
+/// take.
+#[cfg(feature = "fuzz")]
+pub struct Day07Backend;
+
+#[cfg(feature = "fuzz")]
+impl Backend for Day07Backend {
+    fn name(&self) -> &'static str {
+        "day-07 i32"
+    }
+
+    fn supports(&self, _profile: Profile) -> bool {
+        true
+    }
+
+    fn run(&self, program: &[i64], input: i64, max_steps: usize) -> Outcome {
+        let memory = match program
+            .iter()
+            .map(|&data| i32::try_from(data))
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(memory) => memory,
+            Err(_) => return Outcome::Crashed("value does not fit in i32".to_string()),
+        };
+        let input = match i32::try_from(input) {
+            Ok(input) => input,
+            Err(_) => return Outcome::Crashed("input does not fit in i32".to_string()),
+        };
+
+        catch_crash(|| {
+            let mut cpu = day07::intcode::Computer::new(memory);
+            for _ in 0..max_steps {
+                cpu.set_input(input);
+            }
+            for _ in 0..max_steps {
+                if cpu.is_halted() {
+                    let mut last_output = None;
+                    while let Some(data) = cpu.get_output() {
+                        last_output = Some(data as i64);
+                    }
+                    return Outcome::Halted {
+                        memory: cpu.get_memory().iter().map(|&data| data as i64).collect(),
+                        last_output,
+                    };
+                }
+                cpu.step();
+            }
+            Outcome::TimedOut
+        })
+    }
+}
+
+/// The shared `i64` computer used from day 9 onwards. Input is queued, so the
+/// input value is pushed once for every step the program could take.
+pub struct ComputerBackend;
+
+impl Backend for ComputerBackend {
+    fn name(&self) -> &'static str {
+        "intcode i64"
+    }
+
+    fn supports(&self, _profile: Profile) -> bool {
+        true
+    }
+
+    fn run(&self, program: &[i64], input: i64, max_steps: usize) -> Outcome {
+        catch_crash(|| {
+            let mut cpu = Computer::new(&program.to_vec());
+            for _ in 0..max_steps {
+                cpu.set_input(input);
+            }
+            for _ in 0..max_steps {
+                if let Some(error) = cpu.get_error() {
+                    return Outcome::Crashed(error.to_string());
+                }
+                if cpu.is_halted() {
+                    let mut last_output = None;
+                    while let Some(data) = cpu.get_output() {
+                        last_output = Some(data);
+                    }
+                    let memory = cpu
+                        .get_memory()
+                        .expect("Checked arithmetic only stores i64 words");
+                    return Outcome::Halted {
+                        memory: memory[..program.len()].to_vec(),
+                        last_output,
+                    };
+                }
+                cpu.step();
+            }
+            Outcome::TimedOut
+        })
+    }
+}
+
+fn run_interpreter<E: Engine>(program: &[i64], input: i64, max_steps: usize) -> Outcome {
+    catch_crash(|| {
+        let mut cpu = Interpreter::<E>::new(&program.to_vec());
+        for _ in 0..max_steps {
+            cpu.set_input(input);
+        }
+        for _ in 0..max_steps {
+            if cpu.is_halted() {
+                let mut last_output = None;
+                while let Some(data) = cpu.get_output() {
+                    last_output = Some(data);
+                }
+                return Outcome::Halted {
+                    memory: cpu.get_memory()[..program.len()].to_vec(),
+                    last_output,
+                };
+            }
+            if let Err(message) = cpu.step() {
+                return Outcome::Crashed(message);
+            }
+        }
+        Outcome::TimedOut
+    })
+}
+
+/// The interpreter that decodes each instruction once and caches it.
+pub struct DecodedBackend;
+
+impl Backend for DecodedBackend {
+    fn name(&self) -> &'static str {
+        "pre-decoded"
+    }
+
+    fn supports(&self, _profile: Profile) -> bool {
+        true
+    }
+
+    fn run(&self, program: &[i64], input: i64, max_steps: usize) -> Outcome {
+        run_interpreter::<DecodeOnce>(program, input, max_steps)
+    }
+}
+
+/// The interpreter that compiles each instruction into a closure.
+pub struct ThreadedBackend;
+
+impl Backend for ThreadedBackend {
+    fn name(&self) -> &'static str {
+        "threaded"
+    }
+
+    fn supports(&self, _profile: Profile) -> bool {
+        true
+    }
+
+    fn run(&self, program: &[i64], input: i64, max_steps: usize) -> Outcome {
+        run_interpreter::<Closures>(program, input, max_steps)
+    }
+}
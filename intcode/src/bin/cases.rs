@@ -0,0 +1,29 @@
+use intcode::cases::run_dir;
+
+use std::env;
+use std::path::Path;
+use std::process;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 2 {
+        eprintln!("usage: cases <dir>");
+        process::exit(2);
+    }
+
+    // Crashes are reported per case, so keep the panic messages quiet.
+    std::panic::set_hook(Box::new(|_| {}));
+
+    match run_dir(Path::new(&args[1])) {
+        Ok(report) => {
+            println!("{}", report);
+            if report.failures() > 0 {
+                process::exit(1);
+            }
+        }
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use intcode::backends::{all_backends, Profile};
+use intcode::fuzz::{FuzzOptions, Harness};
+
+use std::env;
+use std::panic;
+use std::process;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 || args.len() > 4 {
+        eprintln!("usage: fuzz <day2|day5> [seed] [iterations]");
+        process::exit(2);
+    }
+
+    let profile = match args[1].as_str() {
+        "day2" => Profile::Day2,
+        "day5" => Profile::Day5,
+        other => {
+            eprintln!("unknown profile {:?}", other);
+            process::exit(2);
+        }
+    };
+    let seed = args.get(2).map_or(1, |text| text.parse().expect("Bad seed"));
+    let iterations = args
+        .get(3)
+        .map_or(10_000, |text| text.parse().expect("Bad iteration count"));
+
+    // Crashing interpreters are expected here, so keep their panics quiet.
+    panic::set_hook(Box::new(|_| {}));
+
+    let harness = Harness::new(all_backends(), FuzzOptions::new(profile));
+    let divergences = harness.fuzz(seed, iterations);
+    let _ = panic::take_hook();
+
+    for (i, divergence) in divergences.iter().enumerate() {
+        println!("divergence {}:", i + 1);
+        println!("{}", divergence);
+    }
+    println!(
+        "{} divergences in {} programs (seed {})",
+        divergences.len(),
+        iterations,
+        seed
+    );
+
+    if !divergences.is_empty() {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,70 @@
+use intcode::load_program;
+use intcode::server::{serve, Session};
+
+use std::env;
+use std::io::{self, BufReader};
+use std::net::TcpListener;
+use std::os::unix::net::UnixListener;
+use std::process;
+use std::sync::Arc;
+use std::thread;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        eprintln!("usage: serve <program.intcode> <tcp:HOST:PORT | unix:PATH>");
+        process::exit(2);
+    }
+
+    let program = Arc::new(load_program(&args[1]));
+    let address = &args[2];
+
+    let result = if let Some(addr) = address.strip_prefix("tcp:") {
+        serve_tcp(addr, program)
+    } else if let Some(path) = address.strip_prefix("unix:") {
+        serve_unix(path, program)
+    } else {
+        eprintln!("address must start with tcp: or unix:");
+        process::exit(2);
+    };
+
+    if let Err(err) = result {
+        eprintln!("server failed: {}", err);
+        process::exit(1);
+    }
+}
+
+fn serve_tcp(addr: &str, program: Arc<Vec<i64>>) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    println!("serving on tcp {}", listener.local_addr()?);
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let reader = BufReader::new(stream.try_clone()?);
+        spawn_session(program.clone(), reader, stream);
+    }
+    Ok(())
+}
+
+fn serve_unix(path: &str, program: Arc<Vec<i64>>) -> io::Result<()> {
+    let listener = UnixListener::bind(path)?;
+    println!("serving on unix {}", path);
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let reader = BufReader::new(stream.try_clone()?);
+        spawn_session(program.clone(), reader, stream);
+    }
+    Ok(())
+}
+
+fn spawn_session<R, W>(program: Arc<Vec<i64>>, reader: R, writer: W)
+where
+    R: io::BufRead + Send + 'static,
+    W: io::Write + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut session = Session::new(&program);
+        if let Err(err) = serve(&mut session, reader, writer) {
+            eprintln!("session ended: {}", err);
+        }
+    });
+}
@@ -0,0 +1,440 @@
+//! Declarative test cases. A case file holds one or more cases separated by
+//! `---` lines, each written as `key: value` lines:
+//!
+//! ```text
+//! # Outputs 1 if the input is 8, otherwise 0.
+//! name: equal to 8
+//! memory: 3,9,8,9,10,9,4,9,99,-1,8
+//! input: 8
+//! output: 1
+//! ---
+//! name: not equal to 8
+//! input: 7
+//! output: 0
+//! ```
+//!
+//! `memory` gives the program inline and `program` names an `.intcode` file
+//! relative to the case file. Each may be left out to reuse the previous
+//! case's program, and the same goes for `max-steps`. `expect-memory: 5 =
+//! 9801, 0` checks the cells starting at address 5 and can be repeated. A
+//! case with no `output` line does not check output.
+
+use crate::backends::catch_panic;
+use crate::computer::{Computer, Memory};
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+const DEFAULT_MAX_STEPS: usize = 1_000_000;
+const EXTENSION: &str = "case";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryRange {
+    pub addr: usize,
+    pub values: Vec<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestCase {
+    pub name: String,
+    pub program: Memory,
+    pub input: Vec<i64>,
+    pub output: Option<Vec<i64>>,
+    pub memory: Vec<MemoryRange>,
+    pub max_steps: usize,
+}
+
+fn parse_values(text: &str) -> Result<Vec<i64>, String> {
+    text.split(',')
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            value
+                .parse::<i64>()
+                .map_err(|_| format!("{:?} is not an integer", value))
+        })
+        .collect()
+}
+
+fn parse_range(text: &str) -> Result<MemoryRange, String> {
+    let mut parts = text.splitn(2, '=');
+    let addr = parts.next().unwrap().trim();
+    let values = parts
+        .next()
+        .ok_or_else(|| "expected ADDR = VALUES".to_string())?;
+    Ok(MemoryRange {
+        addr: addr
+            .parse()
+            .map_err(|_| format!("{:?} is not an address", addr))?,
+        values: parse_values(values)?,
+    })
+}
+
+/// Parses the cases in one file. `name` prefixes cases without a `name`
+/// line and `dir` is where `program` paths are looked up.
+pub fn parse_cases(name: &str, text: &str, dir: &Path) -> Result<Vec<TestCase>, String> {
+    let mut cases = Vec::new();
+    let mut program: Option<Memory> = None;
+    let mut max_steps = DEFAULT_MAX_STEPS;
+    let mut case: Option<TestCase> = None;
+
+    for (line_idx, line) in text.lines().enumerate().chain(Some((0, "---"))) {
+        let line = line.trim();
+        let error = |message: String| format!("{} line {}: {}", name, line_idx + 1, message);
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line == "---" {
+            if let Some(mut case) = case.take() {
+                case.program = program
+                    .clone()
+                    .ok_or_else(|| format!("{}: no program or memory", case.name))?;
+                case.max_steps = max_steps;
+                cases.push(case);
+            }
+            continue;
+        }
+
+        let case = case.get_or_insert_with(|| TestCase {
+            name: format!("{}#{}", name, cases.len() + 1),
+            program: Vec::new(),
+            input: Vec::new(),
+            output: None,
+            memory: Vec::new(),
+            max_steps,
+        });
+
+        let mut parts = line.splitn(2, ':');
+        let key = parts.next().unwrap().trim();
+        let value = parts
+            .next()
+            .ok_or_else(|| error("expected KEY: VALUE".to_string()))?
+            .trim();
+        match key {
+            "name" => case.name = value.to_string(),
+            "memory" => program = Some(parse_values(value).map_err(error)?),
+            "program" => {
+                let path = dir.join(value);
+                if !path.is_file() {
+                    return Err(error(format!("no program at {}", path.display())));
+                }
+                let text = fs::read_to_string(&path)
+                    .map_err(|message| error(format!("{}: {}", path.display(), message)))?;
+                let values = parse_values(&text)
+                    .map_err(|message| error(format!("{}: {}", path.display(), message)))?;
+                program = Some(values);
+            }
+            "input" => case.input = parse_values(value).map_err(error)?,
+            "output" => case.output = Some(parse_values(value).map_err(error)?),
+            "expect-memory" => case.memory.push(parse_range(value).map_err(error)?),
+            "max-steps" => {
+                max_steps = value
+                    .parse()
+                    .map_err(|_| error(format!("{:?} is not a step count", value)))?
+            }
+            other => return Err(error(format!("unknown key {:?}", other))),
+        }
+    }
+
+    Ok(cases)
+}
+
+/// Loads every `.case` file in `dir`, in file name order.
+pub fn load_dir(dir: &Path) -> Result<Vec<TestCase>, String> {
+    let mut paths = fs::read_dir(dir)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|error| format!("{}: {}", dir.display(), error))?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == EXTENSION));
+    paths.sort();
+
+    let mut cases = Vec::new();
+    for path in paths {
+        let text =
+            fs::read_to_string(&path).map_err(|error| format!("{}: {}", path.display(), error))?;
+        let name = path.file_stem().unwrap().to_string_lossy();
+        cases.extend(parse_cases(&name, &text, dir)?);
+    }
+    Ok(cases)
+}
+
+/// Lines up expected and actual values by position, numbering rows from
+/// `start` and marking each `=` when they agree and `!` when they differ.
+/// A row past the end of either list shows `-`, and an actual value of
+/// `None` shows `none`.
+pub fn diff_values(start: usize, expected: &[i64], actual: &[Option<i64>]) -> String {
+    let show = |value: Option<Option<i64>>| match value {
+        Some(Some(data)) => data.to_string(),
+        Some(None) => "none".to_string(),
+        None => "-".to_string(),
+    };
+    let rows = (0..expected.len().max(actual.len()))
+        .map(|i| {
+            let expected = expected.get(i).map(|&data| Some(data));
+            (i, show(expected), show(actual.get(i).copied()))
+        })
+        .collect::<Vec<_>>();
+    let width = rows
+        .iter()
+        .map(|(_, expected, _)| expected.len())
+        .max()
+        .unwrap_or(0)
+        .max("expected".len());
+
+    let mut diff = format!("       {:<width$}  actual\n", "expected", width = width);
+    for (i, expected, actual) in rows {
+        let mark = if expected == actual { '=' } else { '!' };
+        diff.push_str(&format!(
+            "{} {:>3}  {:<width$}  {}\n",
+            mark,
+            start + i,
+            expected,
+            actual,
+            width = width
+        ));
+    }
+    diff
+}
+
+/// Runs one case. The error lists everything that went wrong.
+pub fn run_case(case: &TestCase) -> Result<(), String> {
+    let result = catch_panic(|| {
+        let mut cpu = Computer::new(&case.program);
+        for &data in &case.input {
+            cpu.set_input(data);
+        }
+        let mut steps = 0;
+        while steps < case.max_steps
+            && !cpu.is_halted()
+            && !cpu.is_blocked()
+            && cpu.get_error().is_none()
+        {
+            cpu.step();
+            steps += 1;
+        }
+        cpu
+    });
+    let mut cpu = match result {
+        Ok(cpu) => cpu,
+        Err(message) => return Err(format!("crashed: {}\n", message)),
+    };
+
+    let mut problems = String::new();
+    if let Some(error) = cpu.get_error() {
+        problems.push_str(&format!("failed: {}\n", error));
+    } else if cpu.is_blocked() {
+        problems.push_str(&format!("waiting for input at pc {}\n", cpu.get_pc()));
+    } else if !cpu.is_halted() {
+        problems.push_str(&format!("still running after {} steps\n", case.max_steps));
+    }
+
+    if let Some(expected) = &case.output {
+        let mut actual = Vec::new();
+        while let Some(data) = cpu.get_output() {
+            actual.push(data);
+        }
+        if &actual != expected {
+            problems.push_str("output differs:\n");
+            let actual = actual.into_iter().map(Some).collect::<Vec<_>>();
+            problems.push_str(&diff_values(0, expected, &actual));
+        }
+    }
+
+    // A cell that is out of range or too wide for `i64` shows as `none`.
+    for range in &case.memory {
+        let actual = (range.addr..range.addr + range.values.len())
+            .map(|addr| cpu.peek(addr))
+            .collect::<Vec<_>>();
+        let expected = range
+            .values
+            .iter()
+            .map(|&data| Some(data))
+            .collect::<Vec<_>>();
+        if actual != expected {
+            problems.push_str(&format!("memory at {} differs:\n", range.addr));
+            problems.push_str(&diff_values(range.addr, &range.values, &actual));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems)
+    }
+}
+
+pub struct Report {
+    pub results: Vec<(String, Result<(), String>)>,
+}
+
+impl Report {
+    pub fn failures(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|(_, result)| result.is_err())
+            .count()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, result) in &self.results {
+            match result {
+                Ok(()) => writeln!(f, "ok    {}", name)?,
+                Err(problems) => {
+                    writeln!(f, "FAIL  {}", name)?;
+                    for line in problems.lines() {
+                        writeln!(f, "      {}", line)?;
+                    }
+                }
+            }
+        }
+        write!(
+            f,
+            "{} passed, {} failed",
+            self.results.len() - self.failures(),
+            self.failures()
+        )
+    }
+}
+
+/// Runs every case in `dir`.
+pub fn run_dir(dir: &Path) -> Result<Report, String> {
+    let results = load_dir(dir)?
+        .into_iter()
+        .map(|case| {
+            let result = run_case(&case);
+            (case.name, result)
+        })
+        .collect();
+    Ok(Report { results })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::process;
+
+    fn parse(text: &str) -> Result<Vec<TestCase>, String> {
+        parse_cases("test", text, Path::new("."))
+    }
+
+    #[test]
+    fn test_parse_carries_program_over() {
+        let cases = parse(
+            "memory: 3,0,4,0,99\ninput: 5\noutput: 5\nmax-steps: 10\n---\n\
+             # Same program.\nname: echo 6\ninput: 6\nexpect-memory: 0 = 6, 0\n",
+        )
+        .unwrap();
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].name, "test#1");
+        assert_eq!(cases[1].name, "echo 6");
+        assert_eq!(cases[1].program, vec![3, 0, 4, 0, 99]);
+        assert_eq!(cases[1].max_steps, 10);
+        assert_eq!(cases[1].output, None);
+        assert_eq!(
+            cases[1].memory,
+            vec![MemoryRange {
+                addr: 0,
+                values: vec![6, 0]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse("input: 1\n").unwrap_err(),
+            "test#1: no program or memory"
+        );
+        assert_eq!(
+            parse("memory: 99\n\ninput: x\n").unwrap_err(),
+            "test line 3: \"x\" is not an integer"
+        );
+        assert_eq!(
+            parse("memory: 99\nouput: 1\n").unwrap_err(),
+            "test line 2: unknown key \"ouput\""
+        );
+    }
+
+    #[test]
+    fn test_parse_program_file() {
+        let dir = env::temp_dir().join(format!("intcode-cases-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("halt.intcode"), "99,0\n").unwrap();
+        fs::write(dir.join("bad.intcode"), "99,x\n").unwrap();
+
+        let cases = parse_cases("test", "program: halt.intcode\n", &dir).unwrap();
+        assert_eq!(cases[0].program, vec![99, 0]);
+        let message = parse_cases("test", "program: bad.intcode\n", &dir).unwrap_err();
+        assert!(message.starts_with("test line 1: "));
+        assert!(message.ends_with("bad.intcode: \"x\" is not an integer"));
+        assert!(parse_cases("test", "program: missing.intcode\n", &dir).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_run_case_reports_differences() {
+        let mut cases =
+            parse("memory: 104,1,104,2,99\noutput: 1,3,4\nexpect-memory: 4 = 98\n").unwrap();
+        let problems = run_case(&cases[0]).unwrap_err();
+        assert_eq!(
+            problems,
+            "output differs:\n\
+             \x20      expected  actual\n\
+             =   0  1         1\n\
+             !   1  3         2\n\
+             !   2  4         -\n\
+             memory at 4 differs:\n\
+             \x20      expected  actual\n\
+             !   4  98        99\n"
+        );
+
+        // Memory ends at 10004, so the last cell cannot be read.
+        cases[0].output = Some(vec![1, 2]);
+        cases[0].memory = vec![parse_range("10003 = 0,0,0").unwrap()];
+        assert_eq!(
+            run_case(&cases[0]).unwrap_err(),
+            "memory at 10003 differs:\n\
+             \x20      expected  actual\n\
+             = 10003  0         0\n\
+             = 10004  0         0\n\
+             ! 10005  0         none\n"
+        );
+
+        cases[0].memory.clear();
+        assert_eq!(run_case(&cases[0]), Ok(()));
+
+        cases[0].program = vec![3, 0, 99];
+        assert_eq!(
+            run_case(&cases[0]).unwrap_err().lines().next(),
+            Some("waiting for input at pc 0")
+        );
+        cases[0].program = vec![1105, 1, 3, 1105, 1, 0];
+        cases[0].max_steps = 5;
+        assert!(run_case(&cases[0])
+            .unwrap_err()
+            .starts_with("still running after 5 steps"));
+        cases[0].program = vec![42];
+        assert_eq!(
+            run_case(&cases[0]).unwrap_err().lines().next(),
+            Some("failed: unknown op code 42 at pc 0")
+        );
+    }
+
+    #[test]
+    fn test_case_dir() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("cases");
+        let report = run_dir(&dir).unwrap();
+        assert!(report.results.len() > 10);
+        assert_eq!(report.failures(), 0, "\n{}", report);
+    }
+}
@@ -0,0 +1,1320 @@
+use std::char;
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+
+pub type Memory = Vec<i64>;
+
+/// How `add` and `mult` treat results that do not fit in a word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arithmetic {
+    /// Stop the computer with an overflow error.
+    Checked,
+    Wrapping,
+    Saturating,
+    /// Use 128-bit words. Only overflowing those is an error.
+    Wide,
+}
+
+impl Arithmetic {
+    pub fn name(self) -> &'static str {
+        match self {
+            Arithmetic::Checked => "checked",
+            Arithmetic::Wrapping => "wrapping",
+            Arithmetic::Saturating => "saturating",
+            Arithmetic::Wide => "wide",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "checked" => Some(Arithmetic::Checked),
+            "wrapping" => Some(Arithmetic::Wrapping),
+            "saturating" => Some(Arithmetic::Saturating),
+            "wide" => Some(Arithmetic::Wide),
+            _ => None,
+        }
+    }
+
+    fn add(self, x: i128, y: i128) -> Option<i128> {
+        match self {
+            Arithmetic::Checked => (x as i64).checked_add(y as i64).map(i128::from),
+            Arithmetic::Wrapping => Some((x as i64).wrapping_add(y as i64).into()),
+            Arithmetic::Saturating => Some((x as i64).saturating_add(y as i64).into()),
+            Arithmetic::Wide => x.checked_add(y),
+        }
+    }
+
+    fn mult(self, x: i128, y: i128) -> Option<i128> {
+        match self {
+            Arithmetic::Checked => (x as i64).checked_mul(y as i64).map(i128::from),
+            Arithmetic::Wrapping => Some((x as i64).wrapping_mul(y as i64).into()),
+            Arithmetic::Saturating => Some((x as i64).saturating_mul(y as i64).into()),
+            Arithmetic::Wide => x.checked_mul(y),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    Overflow {
+        op: &'static str,
+        x: i128,
+        y: i128,
+    },
+    /// The word at `pc` is too wide to hold an op code and modes.
+    BadInstruction(i128),
+    UnknownOpCode(i64),
+    /// Parameter `param` (counting from 1) has a mode its instruction
+    /// cannot use.
+    BadMode {
+        param: usize,
+        mode: i64,
+    },
+}
+
+/// An error that stopped the computer. `pc` is the failing instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VmError {
+    pub pc: usize,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ErrorKind::Overflow { op, x, y } => {
+                write!(f, "{} {} {} overflowed at pc {}", op, x, y, self.pc)
+            }
+            ErrorKind::BadInstruction(word) => {
+                write!(f, "bad instruction {} at pc {}", word, self.pc)
+            }
+            ErrorKind::UnknownOpCode(op_code) => {
+                write!(f, "unknown op code {} at pc {}", op_code, self.pc)
+            }
+            ErrorKind::BadMode { param, mode } => write!(
+                f,
+                "bad mode {} for parameter {} at pc {}",
+                mode, param, self.pc
+            ),
+        }
+    }
+}
+
+/// One entry in the undo log. A `Step` marks where an instruction began and
+/// holds the registers to go back to; the entries after it undo that step's
+/// writes and queue changes, plus anything done from outside before the next
+/// step.
+#[derive(Debug, Clone)]
+enum Undo {
+    Step {
+        pc: usize,
+        base: i128,
+        blocked: bool,
+    },
+    Write {
+        addr: usize,
+        old: i128,
+    },
+    InputPushed,
+    InputPopped(i128),
+    OutputPushed,
+    OutputPopped(i128),
+}
+
+pub fn load_program(filename: &str) -> Memory {
+    let mut file = File::open(filename).unwrap();
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).unwrap();
+    contents
+        .split(",")
+        .map(|text| text.parse::<i64>().unwrap())
+        .collect::<Vec<_>>()
+}
+
+/// Memory cells. Only `Arithmetic::Wide` needs 128-bit cells; every other
+/// mode narrows its results back to `i64` before they are written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Words {
+    Narrow(Vec<i64>),
+    Wide(Vec<i128>),
+}
+
+impl Words {
+    fn new(memory: &Memory, arithmetic: Arithmetic) -> Self {
+        let len = memory.len() + 10000;
+        match arithmetic {
+            Arithmetic::Wide => {
+                let mut words = memory.iter().map(|&data| data.into()).collect::<Vec<_>>();
+                words.resize(len, 0);
+                Words::Wide(words)
+            }
+            _ => {
+                let mut words = memory.clone();
+                words.resize(len, 0);
+                Words::Narrow(words)
+            }
+        }
+    }
+
+    /// Takes memory as `get_wide_memory` returns it. `None` if a narrow
+    /// mode was asked for and a cell does not fit in `i64`.
+    fn from_wide(words: Vec<i128>, arithmetic: Arithmetic) -> Option<Self> {
+        match arithmetic {
+            Arithmetic::Wide => Some(Words::Wide(words)),
+            _ => words
+                .into_iter()
+                .map(|data| i64::try_from(data).ok())
+                .collect::<Option<Vec<_>>>()
+                .map(Words::Narrow),
+        }
+    }
+
+    fn get(&self, addr: usize) -> Option<i128> {
+        match self {
+            Words::Narrow(words) => words.get(addr).map(|&data| data.into()),
+            Words::Wide(words) => words.get(addr).cloned(),
+        }
+    }
+
+    fn at(&self, addr: usize) -> i128 {
+        match self {
+            Words::Narrow(words) => words[addr].into(),
+            Words::Wide(words) => words[addr],
+        }
+    }
+
+    fn set(&mut self, addr: usize, data: i128) {
+        match self {
+            // Narrow modes only ever produce `i64` results.
+            Words::Narrow(words) => words[addr] = data as i64,
+            Words::Wide(words) => words[addr] = data,
+        }
+    }
+}
+
+/// Words are handled as `i128` so that `Arithmetic::Wide` can share the
+/// interpreter, but only that mode stores them that wide. Cloning a
+/// computer snapshots its whole state.
+#[derive(Clone)]
+pub struct Computer {
+    memory: Words,
+    pc: usize,
+    base: i128,
+    halted: bool,
+    blocked: bool,
+    arithmetic: Arithmetic,
+    error: Option<VmError>,
+    input: VecDeque<i128>,
+    output: VecDeque<i128>,
+    history: VecDeque<Undo>,
+    history_limit: usize,
+    history_steps: usize,
+}
+
+impl Computer {
+    pub fn new(memory: &Memory) -> Computer {
+        Computer::with_arithmetic(memory, Arithmetic::Checked)
+    }
+
+    pub fn with_arithmetic(memory: &Memory, arithmetic: Arithmetic) -> Computer {
+        Computer {
+            memory: Words::new(memory, arithmetic),
+            pc: 0,
+            base: 0,
+            halted: false,
+            blocked: false,
+            arithmetic,
+            error: None,
+            input: VecDeque::new(),
+            output: VecDeque::new(),
+            history: VecDeque::new(),
+            history_limit: 0,
+            history_steps: 0,
+        }
+    }
+
+    /// Keeps enough history to undo the last `limit` steps. Each step only
+    /// logs what it changed, so this is cheap to leave on. A limit of zero
+    /// turns the log off and drops what was recorded.
+    pub fn enable_history(&mut self, limit: usize) {
+        self.history_limit = limit;
+        while self.history_steps > limit {
+            self.forget_oldest_step();
+        }
+    }
+
+    /// How many steps can currently be undone.
+    pub fn history_len(&self) -> usize {
+        self.history_steps
+    }
+
+    /// Undoes up to `n` steps, returning how many were undone. Input queued
+    /// and output taken between steps are put back too, so the computer ends
+    /// up exactly as it was before the earliest undone step.
+    pub fn step_back(&mut self, n: usize) -> usize {
+        let mut undone = 0;
+        while undone < n && self.undo_step() {
+            undone += 1;
+        }
+        undone
+    }
+
+    /// Undoes steps until `predicate` holds, checking after each one.
+    /// Returns false if the history ran out first.
+    pub fn run_back_until<P>(&mut self, predicate: P) -> bool
+    where
+        P: Fn(&Computer) -> bool,
+    {
+        while self.undo_step() {
+            if predicate(self) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn record(&mut self, undo: Undo) {
+        if self.history_steps > 0 {
+            self.history.push_back(undo);
+        }
+    }
+
+    fn begin_step(&mut self) {
+        if self.history_limit == 0 {
+            return;
+        }
+        if self.history_steps == self.history_limit {
+            self.forget_oldest_step();
+        }
+        self.history.push_back(Undo::Step {
+            pc: self.pc,
+            base: self.base,
+            blocked: self.blocked,
+        });
+        self.history_steps += 1;
+    }
+
+    fn forget_oldest_step(&mut self) {
+        self.history.pop_front();
+        while let Some(undo) = self.history.front() {
+            if let Undo::Step { .. } = undo {
+                break;
+            }
+            self.history.pop_front();
+        }
+        self.history_steps -= 1;
+    }
+
+    fn undo_step(&mut self) -> bool {
+        while let Some(undo) = self.history.pop_back() {
+            match undo {
+                Undo::Step { pc, base, blocked } => {
+                    // Steps only run on a live computer, so these were clear.
+                    self.pc = pc;
+                    self.base = base;
+                    self.blocked = blocked;
+                    self.halted = false;
+                    self.error = None;
+                    self.history_steps -= 1;
+                    return true;
+                }
+                Undo::Write { addr, old } => self.memory.set(addr, old),
+                Undo::InputPushed => {
+                    self.input.pop_back();
+                }
+                Undo::InputPopped(data) => self.input.push_front(data),
+                Undo::OutputPushed => {
+                    self.output.pop_back();
+                }
+                Undo::OutputPopped(data) => self.output.push_front(data),
+            }
+        }
+        false
+    }
+
+    /// The whole machine as `key value` lines, which `restore` reads back.
+    /// The undo log and any error are left out.
+    pub fn save(&self) -> String {
+        format!(
+            "arithmetic {}\npc {}\nbase {}\nhalted {}\nblocked {}\nmemory {}\ninput {}\noutput {}\n",
+            self.arithmetic.name(),
+            self.pc,
+            self.base,
+            self.halted,
+            self.blocked,
+            join_list(self.get_wide_memory().iter()),
+            join_list(self.input.iter()),
+            join_list(self.output.iter())
+        )
+    }
+
+    /// Rebuilds a machine from `save`'s output. Keys it does not know are
+    /// skipped, so the lines can share a file with other state. Without an
+    /// `arithmetic` line the machine is checked.
+    pub fn restore(text: &str) -> Result<Computer, String> {
+        let fields = parse_fields(text);
+        let memory = parse_list(&fields, "memory")?;
+        let arithmetic = match fields.get("arithmetic") {
+            Some(name) => {
+                Arithmetic::from_name(name).ok_or_else(|| format!("bad arithmetic {:?}", name))?
+            }
+            None => Arithmetic::Checked,
+        };
+        Ok(Computer {
+            memory: Words::from_wide(memory, arithmetic).ok_or_else(|| {
+                format!("memory is too wide for {} arithmetic", arithmetic.name())
+            })?,
+            pc: parse_field(&fields, "pc")?,
+            base: parse_field(&fields, "base")?,
+            halted: parse_field(&fields, "halted")?,
+            blocked: parse_field(&fields, "blocked")?,
+            arithmetic,
+            error: None,
+            input: parse_list(&fields, "input")?.into(),
+            output: parse_list(&fields, "output")?.into(),
+            history: VecDeque::new(),
+            history_limit: 0,
+            history_steps: 0,
+        })
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    pub fn is_blocked(&self) -> bool {
+        self.blocked
+    }
+
+    pub fn get_pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn get_arithmetic(&self) -> Arithmetic {
+        self.arithmetic
+    }
+
+    /// The error that stopped the computer, if any. A computer with an
+    /// error never steps again.
+    pub fn get_error(&self) -> Option<&VmError> {
+        self.error.as_ref()
+    }
+
+    /// All of memory, or `None` if a cell only fits `get_wide_memory`. That
+    /// can only happen with `Arithmetic::Wide`.
+    pub fn get_memory(&self) -> Option<Memory> {
+        match &self.memory {
+            Words::Narrow(words) => Some(words.clone()),
+            Words::Wide(words) => words.iter().map(|&data| i64::try_from(data).ok()).collect(),
+        }
+    }
+
+    pub fn get_wide_memory(&self) -> Vec<i128> {
+        match &self.memory {
+            Words::Narrow(words) => words.iter().map(|&data| data.into()).collect(),
+            Words::Wide(words) => words.clone(),
+        }
+    }
+
+    /// Reads one cell. `None` if the address is out of range or the cell
+    /// holds a wide value.
+    pub fn peek(&self, addr: usize) -> Option<i64> {
+        self.memory
+            .get(addr)
+            .and_then(|data| i64::try_from(data).ok())
+    }
+
+    /// Writes one cell, returning false if the address is out of range.
+    pub fn poke(&mut self, addr: usize, data: i64) -> bool {
+        match self.memory.get(addr) {
+            Some(old) => {
+                self.memory.set(addr, data.into());
+                self.record(Undo::Write { addr, old });
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn set_input(&mut self, input: i64) {
+        self.input.push_back(input.into());
+        self.record(Undo::InputPushed);
+        self.blocked = false;
+    }
+
+    /// Takes the next output. `None` if there is none, or if it only fits
+    /// `get_wide_output`, in which case it stays queued.
+    pub fn get_output(&mut self) -> Option<i64> {
+        let data = i64::try_from(*self.output.front()?).ok()?;
+        self.output.pop_front();
+        self.record(Undo::OutputPopped(data.into()));
+        Some(data)
+    }
+
+    pub fn get_wide_output(&mut self) -> Option<i128> {
+        let data = self.output.pop_front();
+        if let Some(data) = data {
+            self.record(Undo::OutputPopped(data));
+        }
+        data
+    }
+
+    pub fn has_output(&self) -> bool {
+        !self.output.is_empty()
+    }
+
+    pub fn flush_output(&mut self) -> Option<i64> {
+        let mut result = None;
+        while let Some(data) = self.get_output() {
+            if let Some(c) = char::from_u32(data as u32) {
+                print!("{}", c);
+            } else {
+                result = Some(data);
+                break;
+            }
+        }
+        io::stdout().flush().unwrap();
+        result
+    }
+
+    pub fn send_message(&mut self, message: &str) {
+        for c in message.chars() {
+            let data = c as i64;
+            self.set_input(data);
+        }
+        self.set_input('\n' as i64);
+    }
+
+    #[allow(dead_code)]
+    pub fn run(&mut self) {
+        while !self.halted && !self.blocked && self.error.is_none() {
+            self.step();
+        }
+    }
+
+    pub fn step(&mut self) {
+        if self.halted || self.error.is_some() {
+            return;
+        }
+
+        self.begin_step();
+        let prev_pc = self.pc;
+
+        let op_code = match self
+            .get_op_code()
+            .and_then(|op_code| self.check_modes(op_code))
+        {
+            Ok(op_code) => op_code,
+            Err(kind) => {
+                self.error = Some(VmError { pc: self.pc, kind });
+                return;
+            }
+        };
+
+        let inc = match op_code {
+            1 => self.exec_add(),
+            2 => self.exec_mult(),
+            3 => self.exec_input(),
+            4 => self.exec_output(),
+            5 => self.exec_jump_if_true(),
+            6 => self.exec_jump_if_false(),
+            7 => self.exec_less_than(),
+            8 => self.exec_equals(),
+            9 => self.exec_set_base(),
+            99 => self.exec_halt(),
+            _ => unreachable!("check_modes rejects unknown op codes"),
+        };
+
+        if self.pc == prev_pc {
+            self.pc += inc;
+        }
+    }
+
+    fn data_to_addr(&self, data: i128) -> usize {
+        if data < 0 {
+            0
+        } else {
+            data as usize
+        }
+    }
+
+    fn read(&self, addr: i128) -> i128 {
+        let final_addr = self.data_to_addr(addr);
+        self.memory.at(final_addr)
+    }
+
+    fn read_relative(&self, addr: i128) -> i128 {
+        let final_addr = self.data_to_addr(self.base + addr);
+        self.memory.at(final_addr)
+    }
+
+    fn write(&mut self, addr: i128, data: i128) {
+        let final_addr = self.data_to_addr(addr);
+        self.record(Undo::Write {
+            addr: final_addr,
+            old: self.memory.at(final_addr),
+        });
+        self.memory.set(final_addr, data);
+    }
+
+    fn write_relative(&mut self, addr: i128, data: i128) {
+        self.write(self.base + addr, data);
+    }
+
+    fn read_pc(&self) -> i128 {
+        self.memory.at(self.pc)
+    }
+
+    fn read_pc_offset(&self, offset: usize) -> i128 {
+        self.memory.at(self.pc + offset)
+    }
+
+    fn read_instruction(&self) -> Result<i64, ErrorKind> {
+        let word = self.read_pc();
+        i64::try_from(word).map_err(|_| ErrorKind::BadInstruction(word))
+    }
+
+    fn get_op_code(&self) -> Result<i64, ErrorKind> {
+        let mut op_code = self.read_instruction()?;
+        if op_code > 99 {
+            let digits = to_digits(op_code).take(2).collect::<Vec<_>>();
+            op_code = digits[0] + digits[1] * 10;
+        }
+        Ok(op_code)
+    }
+
+    /// Checks every parameter's mode, so the instruction can run without
+    /// meeting a mode it does not understand. Writes only take position and
+    /// relative modes; a missing or `1` mode digit means position.
+    fn check_modes(&self, op_code: i64) -> Result<i64, ErrorKind> {
+        let writes: &[bool] = match op_code {
+            1 | 2 | 7 | 8 => &[false, false, true],
+            3 => &[true],
+            4 | 9 => &[false],
+            5 | 6 => &[false, false],
+            99 => &[],
+            _ => return Err(ErrorKind::UnknownOpCode(op_code)),
+        };
+        for (i, &write) in writes.iter().enumerate() {
+            let param = i + 1;
+            match (write, self.get_mode(param)) {
+                (_, None) | (_, Some(1)) | (_, Some(2)) | (false, Some(0)) => {}
+                (_, Some(mode)) => return Err(ErrorKind::BadMode { param, mode }),
+            }
+        }
+        Ok(op_code)
+    }
+
+    /// Only called once `step` has checked the instruction with
+    /// `get_op_code`, so reading it again cannot fail.
+    fn get_mode(&self, param_idx: usize) -> Option<i64> {
+        assert!((1..=3).contains(&param_idx));
+        let digit_idx = param_idx + 1;
+        to_digits(self.read_instruction().ok()?).nth(digit_idx)
+    }
+
+    fn read_param_in(&self, param_idx: usize) -> Option<i128> {
+        self.get_mode(param_idx).or(Some(0)).map(|mode| {
+            let param = self.read_pc_offset(param_idx);
+            match mode {
+                0 => self.read(param),
+                1 => param,
+                2 => self.read_relative(param),
+                _ => unreachable!("check_modes rejects bad read modes"),
+            }
+        })
+    }
+
+    fn write_param_out(&mut self, param_idx: usize, data: i128) {
+        let mode = self.get_mode(param_idx).or(Some(1));
+        let param = self.read_pc_offset(param_idx);
+        match mode {
+            Some(1) => self.write(param, data),
+            Some(2) => self.write_relative(param, data),
+            _ => unreachable!("check_modes rejects bad write modes"),
+        }
+    }
+
+    fn exec_add(&mut self) -> usize {
+        let arithmetic = self.arithmetic;
+        self.execute("add", |x, y| arithmetic.add(x, y))
+    }
+
+    fn exec_mult(&mut self) -> usize {
+        let arithmetic = self.arithmetic;
+        self.execute("mult", |x, y| arithmetic.mult(x, y))
+    }
+
+    fn exec_equals(&mut self) -> usize {
+        self.execute("eq", |x, y| Some(if x == y { 1 } else { 0 }))
+    }
+
+    fn exec_less_than(&mut self) -> usize {
+        self.execute("lt", |x, y| Some(if x < y { 1 } else { 0 }))
+    }
+
+    fn execute<T>(&mut self, op: &'static str, operation: T) -> usize
+    where
+        T: Fn(i128, i128) -> Option<i128>,
+    {
+        let param1 = self.read_param_in(1);
+        let param2 = self.read_param_in(2);
+
+        let (in1, in2) = match (param1, param2) {
+            (Some(in1), Some(in2)) => (in1, in2),
+            _ => unreachable!("read_param_in always reads a parameter"),
+        };
+
+        match operation(in1, in2) {
+            Some(result) => {
+                self.write_param_out(3, result);
+                4
+            }
+            None => {
+                self.error = Some(VmError {
+                    pc: self.pc,
+                    kind: ErrorKind::Overflow { op, x: in1, y: in2 },
+                });
+                0
+            }
+        }
+    }
+
+    fn exec_input(&mut self) -> usize {
+        if let Some(data) = self.input.pop_front() {
+            self.record(Undo::InputPopped(data));
+            self.blocked = false;
+            self.write_param_out(1, data);
+            2
+        } else {
+            self.blocked = true;
+            0 // stall until input is available
+        }
+    }
+
+    fn exec_output(&mut self) -> usize {
+        if let Some(data) = self.read_param_in(1) {
+            self.output.push_back(data);
+            self.record(Undo::OutputPushed);
+        }
+        2
+    }
+
+    fn exec_jump_if_true(&mut self) -> usize {
+        self.exec_jump(|data| data != 0)
+    }
+
+    fn exec_jump_if_false(&mut self) -> usize {
+        self.exec_jump(|data| data == 0)
+    }
+
+    fn exec_jump<T>(&mut self, predicate: T) -> usize
+    where
+        T: Fn(i128) -> bool,
+    {
+        if let Some(data) = self.read_param_in(1) {
+            if predicate(data) {
+                if let Some(addr) = self.read_param_in(2) {
+                    self.pc = addr as usize;
+                }
+            }
+        }
+        3
+    }
+
+    fn exec_set_base(&mut self) -> usize {
+        if let Some(off) = self.read_param_in(1) {
+            self.base += off;
+        }
+        2
+    }
+
+    fn exec_halt(&mut self) -> usize {
+        self.halted = true;
+        1
+    }
+}
+
+fn join_list<'a, I: Iterator<Item = &'a i128>>(data: I) -> String {
+    data.map(|n| n.to_string()).collect::<Vec<_>>().join(",")
+}
+
+/// Splits `key value` lines into a map from key to value.
+pub fn parse_fields(text: &str) -> HashMap<&str, &str> {
+    text.lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, ' ');
+            let key = parts.next().filter(|key| !key.is_empty())?;
+            Some((key, parts.next().unwrap_or("")))
+        })
+        .collect()
+}
+
+pub fn parse_field<T: std::str::FromStr>(
+    fields: &HashMap<&str, &str>,
+    key: &str,
+) -> Result<T, String> {
+    let value = fields.get(key).ok_or_else(|| format!("missing {}", key))?;
+    value
+        .parse()
+        .map_err(|_| format!("bad {} {:?}", key, value))
+}
+
+fn parse_list(fields: &HashMap<&str, &str>, key: &str) -> Result<Vec<i128>, String> {
+    let value = fields.get(key).ok_or_else(|| format!("missing {}", key))?;
+    value
+        .split(',')
+        .filter(|n| !n.is_empty())
+        .map(|n| n.parse().map_err(|_| format!("bad {} {:?}", key, n)))
+        .collect()
+}
+
+pub struct Digits {
+    n: Option<i64>,
+}
+
+impl Digits {
+    fn new(n: i64) -> Self {
+        Digits { n: Some(n) }
+    }
+}
+
+impl Iterator for Digits {
+    type Item = i64;
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.n {
+            None => None,
+            Some(n) => {
+                if n < 10 {
+                    let next = Some(n);
+                    self.n = None;
+                    next
+                } else {
+                    let next = n % 10;
+                    self.n = Some(n / 10);
+                    Some(next)
+                }
+            }
+        }
+    }
+}
+
+pub fn to_digits(n: i64) -> Digits {
+    Digits::new(n)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_digits() {
+        assert_eq!(to_digits(0).collect::<Vec<_>>(), vec![0]);
+        assert_eq!(to_digits(1).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(to_digits(10).collect::<Vec<_>>(), vec![0, 1]);
+        assert_eq!(to_digits(42).collect::<Vec<_>>(), vec![2, 4]);
+        assert_eq!(to_digits(100).collect::<Vec<_>>(), vec![0, 0, 1]);
+    }
+
+    fn expect_program(initial_mem: Memory, expected_mem: Memory) {
+        let mut cpu = Computer::new(&initial_mem);
+        cpu.run();
+        assert_eq!(
+            &cpu.get_memory().unwrap()[0..initial_mem.len()],
+            expected_mem.as_slice()
+        );
+    }
+
+    #[test]
+    fn test_program_1() {
+        expect_program(vec![1, 0, 0, 0, 99], vec![2, 0, 0, 0, 99]);
+    }
+
+    #[test]
+    fn test_program_2() {
+        expect_program(vec![2, 3, 0, 3, 99], vec![2, 3, 0, 6, 99]);
+    }
+
+    #[test]
+    fn test_program_3() {
+        expect_program(vec![2, 4, 4, 5, 99, 0], vec![2, 4, 4, 5, 99, 9801]);
+    }
+
+    #[test]
+    fn test_program_4() {
+        expect_program(
+            vec![1, 1, 1, 4, 99, 5, 6, 0, 99],
+            vec![30, 1, 1, 4, 2, 5, 6, 0, 99],
+        );
+    }
+
+    #[test]
+    fn test_input_output() {
+        let initial_mem = vec![3, 0, 4, 0, 99];
+        let mut cpu = Computer::new(&initial_mem);
+        cpu.set_input(42);
+        cpu.run();
+        assert_eq!(cpu.get_output(), Some(42));
+    }
+
+    #[test]
+    fn test_modes() {
+        expect_program(vec![1002, 4, 3, 4, 33], vec![1002, 4, 3, 4, 99]);
+    }
+
+    #[test]
+    fn test_equal_8() {
+        let initial_mem = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+        let mut cpu = Computer::new(&initial_mem);
+        cpu.set_input(8);
+        cpu.run();
+        assert_eq!(cpu.get_output(), Some(1));
+
+        let initial_mem = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+        let mut cpu = Computer::new(&initial_mem);
+        cpu.set_input(7);
+        cpu.run();
+        assert_eq!(cpu.get_output(), Some(0));
+    }
+
+    #[test]
+    fn test_lt_8() {
+        let initial_mem = vec![3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8];
+        let mut cpu = Computer::new(&initial_mem);
+        cpu.set_input(8);
+        cpu.run();
+        assert_eq!(cpu.get_output(), Some(0));
+
+        let initial_mem = vec![3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8];
+        let mut cpu = Computer::new(&initial_mem);
+        cpu.set_input(7);
+        cpu.run();
+        assert_eq!(cpu.get_output(), Some(1));
+    }
+
+    #[test]
+    fn test_eq_8_immediate() {
+        let initial_mem = vec![3, 3, 1108, -1, 8, 3, 4, 3, 99];
+        let mut cpu = Computer::new(&initial_mem);
+        cpu.set_input(8);
+        cpu.run();
+        assert_eq!(cpu.get_output(), Some(1));
+
+        let initial_mem = vec![3, 3, 1108, -1, 8, 3, 4, 3, 99];
+        let mut cpu = Computer::new(&initial_mem);
+        cpu.set_input(7);
+        cpu.run();
+        assert_eq!(cpu.get_output(), Some(0));
+    }
+
+    #[test]
+    fn test_lt_8_immediate() {
+        let initial_mem = vec![3, 3, 1107, -1, 8, 3, 4, 3, 99];
+        let mut cpu = Computer::new(&initial_mem);
+        cpu.set_input(8);
+        cpu.run();
+        assert_eq!(cpu.get_output(), Some(0));
+
+        let initial_mem = vec![3, 3, 1107, -1, 8, 3, 4, 3, 99];
+        let mut cpu = Computer::new(&initial_mem);
+        cpu.set_input(7);
+        cpu.run();
+        assert_eq!(cpu.get_output(), Some(1));
+    }
+
+    #[test]
+    fn test_jump_position() {
+        let initial_mem = vec![3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9];
+        let mut cpu = Computer::new(&initial_mem);
+        cpu.set_input(0);
+        cpu.run();
+        assert_eq!(cpu.get_output(), Some(0));
+
+        let initial_mem = vec![3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9];
+        let mut cpu = Computer::new(&initial_mem);
+        cpu.set_input(7);
+        cpu.run();
+        assert_eq!(cpu.get_output(), Some(1));
+    }
+
+    #[test]
+    fn test_jump_immediate() {
+        let initial_mem = vec![3, 3, 1105, -1, 9, 1101, 0, 0, 12, 4, 12, 99, 1];
+        let mut cpu = Computer::new(&initial_mem);
+        cpu.set_input(0);
+        cpu.run();
+        assert_eq!(cpu.get_output(), Some(0));
+
+        let initial_mem = vec![3, 3, 1105, -1, 9, 1101, 0, 0, 12, 4, 12, 99, 1];
+        let mut cpu = Computer::new(&initial_mem);
+        cpu.set_input(7);
+        cpu.run();
+        assert_eq!(cpu.get_output(), Some(1));
+    }
+
+    #[test]
+    fn test_compare_8() {
+        let initial_mem = vec![
+            3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0,
+            0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4,
+            20, 1105, 1, 46, 98, 99,
+        ];
+        let mut cpu = Computer::new(&initial_mem);
+        cpu.set_input(7);
+        cpu.run();
+        assert_eq!(cpu.get_output(), Some(999));
+
+        let initial_mem = vec![
+            3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0,
+            0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4,
+            20, 1105, 1, 46, 98, 99,
+        ];
+        let mut cpu = Computer::new(&initial_mem);
+        cpu.set_input(8);
+        cpu.run();
+        assert_eq!(cpu.get_output(), Some(1000));
+
+        let initial_mem = vec![
+            3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0,
+            0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4,
+            20, 1105, 1, 46, 98, 99,
+        ];
+        let mut cpu = Computer::new(&initial_mem);
+        cpu.set_input(9);
+        cpu.run();
+        assert_eq!(cpu.get_output(), Some(1001));
+    }
+
+    #[test]
+    fn test_blocking_input() {
+        let initial_mem = vec![3, 0, 99];
+        let mut cpu = Computer::new(&initial_mem);
+        cpu.step();
+        cpu.step();
+        cpu.step();
+        assert_eq!(cpu.pc, 0);
+        cpu.set_input(42);
+        cpu.step();
+        assert_eq!(cpu.pc, 2);
+    }
+
+    #[test]
+    fn test_relative1() {
+        let mem = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let mut cpu = Computer::new(&mem);
+        cpu.run();
+        let mut copy = Vec::new();
+        while let Some(data) = cpu.get_output() {
+            copy.push(data);
+        }
+        assert_eq!(copy, mem);
+    }
+
+    #[test]
+    fn test_relative2() {
+        let mem = vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0];
+        let mut cpu = Computer::new(&mem);
+        cpu.run();
+        let out_digits = match cpu.get_output() {
+            Some(data) => to_digits(data).collect::<Vec<_>>(),
+            None => vec![],
+        };
+        assert_eq!(out_digits.len(), 16);
+    }
+
+    #[test]
+    fn test_relative3() {
+        let mem = vec![104, 1125899906842624, 99];
+        let mut cpu = Computer::new(&mem);
+        cpu.run();
+        assert_eq!(cpu.get_output(), Some(1125899906842624));
+    }
+
+    fn run_with(arithmetic: Arithmetic, mem: Memory) -> Computer {
+        let mut cpu = Computer::with_arithmetic(&mem, arithmetic);
+        cpu.run();
+        cpu
+    }
+
+    const MULT_OVERFLOW: &[i64] = &[1102, i64::MAX, 2, 7, 4, 7, 99, 0];
+    const ADD_OVERFLOW: &[i64] = &[1101, i64::MIN, -1, 7, 4, 7, 99, 0];
+
+    #[test]
+    fn test_arithmetic_in_range() {
+        for &arithmetic in [
+            Arithmetic::Checked,
+            Arithmetic::Wrapping,
+            Arithmetic::Saturating,
+            Arithmetic::Wide,
+        ]
+        .iter()
+        {
+            let mut cpu = run_with(arithmetic, vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0]);
+            assert_eq!(cpu.get_output(), Some(1219070632396864));
+            assert_eq!(cpu.get_error(), None);
+        }
+    }
+
+    #[test]
+    fn test_checked_overflow() {
+        let mut cpu = run_with(
+            Arithmetic::Checked,
+            vec![1101, 0, 0, 9, 4, 9, 1102, i64::MAX, 2, 7],
+        );
+        assert!(!cpu.is_halted());
+        assert_eq!(cpu.get_output(), Some(0));
+        assert_eq!(
+            cpu.get_error(),
+            Some(&VmError {
+                pc: 6,
+                kind: ErrorKind::Overflow {
+                    op: "mult",
+                    x: i64::MAX.into(),
+                    y: 2
+                }
+            })
+        );
+
+        let cpu = run_with(Arithmetic::Checked, ADD_OVERFLOW.to_vec());
+        assert_eq!(cpu.get_error().map(|error| error.pc), Some(0));
+
+        // A computer that hit an error stays put.
+        let mut cpu = run_with(Arithmetic::Checked, MULT_OVERFLOW.to_vec());
+        cpu.step();
+        assert_eq!(cpu.pc, 0);
+        assert_eq!(cpu.get_output(), None);
+    }
+
+    #[test]
+    fn test_wrapping_overflow() {
+        let mut cpu = run_with(Arithmetic::Wrapping, MULT_OVERFLOW.to_vec());
+        assert_eq!(cpu.get_output(), Some(-2));
+        let mut cpu = run_with(Arithmetic::Wrapping, ADD_OVERFLOW.to_vec());
+        assert_eq!(cpu.get_output(), Some(i64::MAX));
+    }
+
+    #[test]
+    fn test_saturating_overflow() {
+        let mut cpu = run_with(Arithmetic::Saturating, MULT_OVERFLOW.to_vec());
+        assert_eq!(cpu.get_output(), Some(i64::MAX));
+        let mut cpu = run_with(Arithmetic::Saturating, ADD_OVERFLOW.to_vec());
+        assert_eq!(cpu.get_output(), Some(i64::MIN));
+    }
+
+    #[test]
+    fn test_wide_overflow() {
+        let mut cpu = run_with(Arithmetic::Wide, MULT_OVERFLOW.to_vec());
+        assert_eq!(cpu.get_memory(), None);
+        assert_eq!(cpu.peek(7), None);
+        assert_eq!(cpu.get_wide_memory()[7], i128::from(i64::MAX) * 2);
+        // A wide output stays queued for the wide accessor.
+        assert_eq!(cpu.get_output(), None);
+        assert!(cpu.has_output());
+        assert_eq!(cpu.get_wide_output(), Some(i128::from(i64::MAX) * 2));
+
+        let mut cpu = run_with(Arithmetic::Wide, ADD_OVERFLOW.to_vec());
+        assert_eq!(cpu.get_wide_output(), Some(i128::from(i64::MIN) - 1));
+
+        // Squaring past 2^127 is still an error.
+        let mut cpu = run_with(
+            Arithmetic::Wide,
+            vec![2, 9, 9, 9, 1105, 1, 0, 0, 0, i64::MAX],
+        );
+        assert_eq!(cpu.get_error().map(|error| error.pc), Some(0));
+        assert_eq!(cpu.get_wide_output(), None);
+
+        // A wide word where an instruction should be stops the computer.
+        let mut cpu = run_with(
+            Arithmetic::Wide,
+            vec![1102, i64::MAX, 2, 8, 1105, 1, 8, 0, 0],
+        );
+        assert_eq!(
+            cpu.get_error(),
+            Some(&VmError {
+                pc: 8,
+                kind: ErrorKind::BadInstruction(i128::from(i64::MAX) * 2)
+            })
+        );
+        assert_eq!(cpu.get_output(), None);
+    }
+
+    #[test]
+    fn test_bad_op_codes_and_modes() {
+        let cpu = run_with(Arithmetic::Checked, vec![1101, 1, 1, 5, 42, 0]);
+        assert_eq!(
+            cpu.get_error(),
+            Some(&VmError {
+                pc: 4,
+                kind: ErrorKind::UnknownOpCode(42)
+            })
+        );
+        assert_eq!(
+            cpu.get_error().map(|error| error.to_string()),
+            Some("unknown op code 42 at pc 4".to_string())
+        );
+
+        let cpu = run_with(Arithmetic::Checked, vec![301, 0, 0, 0, 99]);
+        assert_eq!(
+            cpu.get_error().map(|error| &error.kind),
+            Some(&ErrorKind::BadMode { param: 1, mode: 3 })
+        );
+        assert_eq!(cpu.get_pc(), 0);
+
+        // The written parameter's mode is checked too.
+        let cpu = run_with(Arithmetic::Checked, vec![30001, 0, 0, 0, 99]);
+        assert_eq!(
+            cpu.get_error().map(|error| &error.kind),
+            Some(&ErrorKind::BadMode { param: 3, mode: 3 })
+        );
+    }
+
+    #[test]
+    fn test_narrow_modes_store_i64() {
+        let cpu = Computer::new(&vec![99]);
+        assert!(matches!(cpu.memory, Words::Narrow(_)));
+        let cpu = Computer::with_arithmetic(&vec![99], Arithmetic::Wide);
+        assert!(matches!(cpu.memory, Words::Wide(_)));
+    }
+
+    #[test]
+    fn test_save_restore() {
+        // Echo two inputs, with the second still queued.
+        let mut cpu = Computer::new(&vec![3, 9, 4, 9, 3, 9, 4, 9, 99, 0]);
+        cpu.set_input(7);
+        cpu.set_input(8);
+        cpu.step();
+        cpu.step();
+
+        let mut restored = Computer::restore(&cpu.save()).unwrap();
+        assert_eq!(restored.save(), cpu.save());
+        restored.run();
+        assert_eq!(restored.get_output(), Some(7));
+        assert_eq!(restored.get_output(), Some(8));
+        assert!(restored.is_halted());
+
+        assert_eq!(
+            Computer::restore("pc 0\nbase x").err(),
+            Some("missing memory".to_string())
+        );
+    }
+
+    #[test]
+    fn test_save_restore_wide() {
+        let cpu = run_with(Arithmetic::Wide, MULT_OVERFLOW.to_vec());
+        let mut restored = Computer::restore(&cpu.save()).unwrap();
+        assert_eq!(restored.get_arithmetic(), Arithmetic::Wide);
+        assert_eq!(restored.get_wide_memory(), cpu.get_wide_memory());
+        assert_eq!(restored.get_wide_output(), Some(i128::from(i64::MAX) * 2));
+
+        // Saves from before the arithmetic line are checked.
+        let save = cpu.save().replace("arithmetic wide\n", "");
+        assert_eq!(
+            Computer::restore(&save).err(),
+            Some("memory is too wide for checked arithmetic".to_string())
+        );
+    }
+
+    fn assert_same_state(a: &Computer, b: &Computer) {
+        assert_eq!(a.memory, b.memory);
+        assert_eq!(a.pc, b.pc);
+        assert_eq!(a.base, b.base);
+        assert_eq!(a.halted, b.halted);
+        assert_eq!(a.blocked, b.blocked);
+        assert_eq!(a.error, b.error);
+        assert_eq!(a.input, b.input);
+        assert_eq!(a.output, b.output);
+    }
+
+    // Reads values and outputs them doubled, using relative mode, until it
+    // reads a zero.
+    const DOUBLER: &[i64] = &[
+        109, 20, 203, 0, 1006, 20, 17, 21002, 20, 2, 1, 204, 1, 1105, 1, 2, 0, 99,
+    ];
+
+    #[test]
+    fn test_step_back_restores_every_state() {
+        let mut cpu = Computer::new(&DOUBLER.to_vec());
+        cpu.enable_history(100);
+        cpu.set_input(3);
+
+        let mut states = Vec::new();
+        while !cpu.is_halted() {
+            states.push(cpu.clone());
+            cpu.step();
+            if cpu.is_blocked() {
+                assert_eq!(cpu.get_output(), Some(6));
+                cpu.set_input(0);
+            }
+        }
+
+        for state in states.iter().rev() {
+            assert_eq!(cpu.step_back(1), 1);
+            assert_same_state(&cpu, state);
+        }
+        assert_eq!(cpu.step_back(1), 0);
+        assert_eq!(cpu.history_len(), 0);
+
+        // Replaying from the start gives the same result.
+        cpu.run();
+        assert_eq!(cpu.get_output(), Some(6));
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut cpu = Computer::new(&DOUBLER.to_vec());
+        cpu.enable_history(3);
+        cpu.set_input(5);
+        cpu.run();
+        assert!(cpu.is_blocked());
+        assert_eq!(cpu.history_len(), 3);
+
+        // The oldest of the three is the output of 10.
+        assert_eq!(cpu.step_back(10), 3);
+        assert_eq!(cpu.pc, 11);
+        assert_eq!(cpu.get_output(), None);
+        assert!(cpu.history.is_empty());
+
+        cpu.step();
+        cpu.enable_history(0);
+        assert!(cpu.history.is_empty());
+        cpu.step();
+        assert_eq!(cpu.history_len(), 0);
+    }
+
+    #[test]
+    fn test_run_back_until() {
+        let mut cpu = Computer::new(&DOUBLER.to_vec());
+        cpu.enable_history(100);
+        for data in &[1, 2, 3, 0] {
+            cpu.set_input(*data);
+        }
+        cpu.run();
+        assert!(cpu.is_halted());
+
+        // Back to just before the second value was doubled.
+        assert!(cpu.run_back_until(|cpu| cpu.pc == 7 && cpu.output.len() == 1));
+        assert!(!cpu.is_halted());
+        assert_eq!(cpu.peek(20), Some(2));
+        assert_eq!(cpu.input, vec![3, 0]);
+
+        assert!(!cpu.run_back_until(|cpu| cpu.pc == 99));
+        assert_eq!(cpu.pc, 0);
+    }
+
+    #[test]
+    fn test_step_back_clears_error() {
+        let mut cpu = Computer::new(&MULT_OVERFLOW.to_vec());
+        cpu.enable_history(10);
+        cpu.run();
+        assert!(cpu.get_error().is_some());
+        assert_eq!(cpu.step_back(1), 1);
+        assert_eq!(cpu.get_error(), None);
+        assert_eq!(cpu.pc, 0);
+    }
+}
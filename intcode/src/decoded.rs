@@ -0,0 +1,551 @@
+//! Interpreters that decode each instruction once instead of on every step.
+//! `Decoded` caches the decoded instruction and `Threaded` caches a closure
+//! compiled from it. A write into a cached instruction throws the cache entry
+//! away, so self-modifying programs still run correctly.
+
+use crate::computer::Memory;
+
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Param {
+    Position(i64),
+    Immediate(i64),
+    Relative(i64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Add(Param, Param, Param),
+    Mult(Param, Param, Param),
+    Input(Param),
+    Output(Param),
+    JumpIfTrue(Param, Param),
+    JumpIfFalse(Param, Param),
+    LessThan(Param, Param, Param),
+    Equals(Param, Param, Param),
+    SetBase(Param),
+    Halt,
+}
+
+/// The longest instruction, which is as far back as a write can reach into
+/// a cached one.
+const MAX_LEN: usize = 4;
+
+/// Decodes the instruction at `pc`. Output parameters in immediate mode are
+/// written as position mode, the same as the `Computer` does.
+pub fn decode(memory: &[i64], pc: usize) -> Result<Instruction, String> {
+    let word = *memory
+        .get(pc)
+        .ok_or_else(|| format!("pc {} is outside memory", pc))?;
+    if word < 0 {
+        return Err(format!("bad instruction {} at pc {}", word, pc));
+    }
+
+    let mode = |n: usize| word / 10_i64.pow(n as u32 + 1) % 10;
+    let value = |n: usize| {
+        memory
+            .get(pc + n)
+            .cloned()
+            .ok_or_else(|| format!("instruction at pc {} runs past memory", pc))
+    };
+    let input = |n: usize| match mode(n) {
+        0 => Ok(Param::Position(value(n)?)),
+        1 => Ok(Param::Immediate(value(n)?)),
+        2 => Ok(Param::Relative(value(n)?)),
+        bad_mode => Err(format!("bad mode {} at pc {}", bad_mode, pc)),
+    };
+    let output = |n: usize| match mode(n) {
+        0 | 1 => Ok(Param::Position(value(n)?)),
+        2 => Ok(Param::Relative(value(n)?)),
+        bad_mode => Err(format!("bad mode {} at pc {}", bad_mode, pc)),
+    };
+
+    let instruction = match word % 100 {
+        1 => Instruction::Add(input(1)?, input(2)?, output(3)?),
+        2 => Instruction::Mult(input(1)?, input(2)?, output(3)?),
+        3 => Instruction::Input(output(1)?),
+        4 => Instruction::Output(input(1)?),
+        5 => Instruction::JumpIfTrue(input(1)?, input(2)?),
+        6 => Instruction::JumpIfFalse(input(1)?, input(2)?),
+        7 => Instruction::LessThan(input(1)?, input(2)?, output(3)?),
+        8 => Instruction::Equals(input(1)?, input(2)?, output(3)?),
+        9 => Instruction::SetBase(input(1)?),
+        99 => Instruction::Halt,
+        bad_op => return Err(format!("unknown op code {} at pc {}", bad_op, pc)),
+    };
+    Ok(instruction)
+}
+
+/// The state both interpreters share. Arithmetic is checked, and addresses
+/// outside memory are errors rather than panics.
+#[derive(Debug, Clone)]
+pub struct Machine {
+    memory: Vec<i64>,
+    pc: usize,
+    base: i64,
+    halted: bool,
+    input: VecDeque<i64>,
+    output: VecDeque<i64>,
+}
+
+impl Machine {
+    fn new(memory: &Memory) -> Self {
+        let mut memory = memory.clone();
+        memory.resize(memory.len() + 10000, 0);
+        Machine {
+            memory,
+            pc: 0,
+            base: 0,
+            halted: false,
+            input: VecDeque::new(),
+            output: VecDeque::new(),
+        }
+    }
+
+    fn addr(&self, data: i64) -> Result<usize, String> {
+        if data < 0 || data as usize >= self.memory.len() {
+            Err(format!("address {} is outside memory", data))
+        } else {
+            Ok(data as usize)
+        }
+    }
+
+    fn read(&self, param: Param) -> Result<i64, String> {
+        match param {
+            Param::Position(addr) => Ok(self.memory[self.addr(addr)?]),
+            Param::Immediate(value) => Ok(value),
+            Param::Relative(offset) => {
+                Ok(self.memory[self.addr(self.base.saturating_add(offset))?])
+            }
+        }
+    }
+
+    /// Writes `data` and returns the address it landed on.
+    fn write(&mut self, param: Param, data: i64) -> Result<usize, String> {
+        let addr = match param {
+            Param::Position(addr) => self.addr(addr)?,
+            Param::Relative(offset) => self.addr(self.base.saturating_add(offset))?,
+            Param::Immediate(_) => return Err("immediate output parameter".to_string()),
+        };
+        self.memory[addr] = data;
+        Ok(addr)
+    }
+
+    /// Like the other interpreters, a jump to the instruction's own address
+    /// falls through to the next instruction.
+    fn jump(&mut self, target: i64, len: usize) -> Result<(), String> {
+        if target < 0 {
+            return Err(format!("jump to negative address {}", target));
+        }
+        if target as usize == self.pc {
+            self.pc += len;
+        } else {
+            self.pc = target as usize;
+        }
+        Ok(())
+    }
+
+    fn arithmetic<F>(&mut self, a: Param, b: Param, c: Param, op: F) -> Result<usize, String>
+    where
+        F: Fn(i64, i64) -> Option<i64>,
+    {
+        let (x, y) = (self.read(a)?, self.read(b)?);
+        let result =
+            op(x, y).ok_or_else(|| format!("overflow at pc {}: {} and {}", self.pc, x, y))?;
+        let addr = self.write(c, result)?;
+        self.pc += 4;
+        Ok(addr)
+    }
+
+    fn input(&mut self, a: Param) -> Result<Option<usize>, String> {
+        match self.input.pop_front() {
+            Some(data) => {
+                let addr = self.write(a, data)?;
+                self.pc += 2;
+                Ok(Some(addr))
+            }
+            None => Ok(None), // stall until input is available
+        }
+    }
+
+    fn output(&mut self, a: Param) -> Result<(), String> {
+        let data = self.read(a)?;
+        self.output.push_back(data);
+        self.pc += 2;
+        Ok(())
+    }
+
+    fn jump_if<P>(&mut self, a: Param, target: Param, predicate: P) -> Result<(), String>
+    where
+        P: Fn(i64) -> bool,
+    {
+        if predicate(self.read(a)?) {
+            let target = self.read(target)?;
+            self.jump(target, 3)
+        } else {
+            self.pc += 3;
+            Ok(())
+        }
+    }
+
+    fn set_base(&mut self, a: Param) -> Result<(), String> {
+        let offset = self.read(a)?;
+        self.base = self
+            .base
+            .checked_add(offset)
+            .ok_or("relative base overflowed")?;
+        self.pc += 2;
+        Ok(())
+    }
+
+    fn halt(&mut self) {
+        self.halted = true;
+        self.pc += 1;
+    }
+
+    /// Runs one decoded instruction and returns the address it wrote to.
+    fn execute(&mut self, instruction: Instruction) -> Result<Option<usize>, String> {
+        let flag = |condition: bool| Some(if condition { 1 } else { 0 });
+        match instruction {
+            Instruction::Add(a, b, c) => self.arithmetic(a, b, c, i64::checked_add).map(Some),
+            Instruction::Mult(a, b, c) => self.arithmetic(a, b, c, i64::checked_mul).map(Some),
+            Instruction::LessThan(a, b, c) => {
+                self.arithmetic(a, b, c, |x, y| flag(x < y)).map(Some)
+            }
+            Instruction::Equals(a, b, c) => self.arithmetic(a, b, c, |x, y| flag(x == y)).map(Some),
+            Instruction::Input(a) => self.input(a),
+            Instruction::Output(a) => self.output(a).map(|_| None),
+            Instruction::JumpIfTrue(a, b) => self.jump_if(a, b, |data| data != 0).map(|_| None),
+            Instruction::JumpIfFalse(a, b) => self.jump_if(a, b, |data| data == 0).map(|_| None),
+            Instruction::SetBase(a) => self.set_base(a).map(|_| None),
+            Instruction::Halt => {
+                self.halt();
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// What an interpreter keeps for each decoded instruction, and how it runs
+/// it.
+pub trait Engine {
+    type Compiled;
+
+    fn compile(instruction: Instruction) -> Self::Compiled;
+
+    /// Runs a compiled instruction and returns the address it wrote to.
+    fn execute(compiled: &Self::Compiled, machine: &mut Machine) -> Result<Option<usize>, String>;
+}
+
+/// Keeps the decoded instruction and matches on it every time it runs.
+pub struct DecodeOnce;
+
+impl Engine for DecodeOnce {
+    type Compiled = Instruction;
+
+    fn compile(instruction: Instruction) -> Instruction {
+        instruction
+    }
+
+    fn execute(instruction: &Instruction, machine: &mut Machine) -> Result<Option<usize>, String> {
+        machine.execute(*instruction)
+    }
+}
+
+type Op = Rc<dyn Fn(&mut Machine) -> Result<Option<usize>, String>>;
+type Read = Box<dyn Fn(&Machine) -> Result<i64, String>>;
+
+/// Turns each instruction into a closure with its parameter modes already
+/// resolved, so running it does no decoding or matching at all.
+pub struct Closures;
+
+fn reader(param: Param) -> Read {
+    match param {
+        Param::Position(addr) => Box::new(move |m| m.read(Param::Position(addr))),
+        Param::Immediate(value) => Box::new(move |_| Ok(value)),
+        Param::Relative(offset) => Box::new(move |m| m.read(Param::Relative(offset))),
+    }
+}
+
+fn compile_arithmetic<F>(a: Param, b: Param, c: Param, op: F) -> Op
+where
+    F: Fn(i64, i64) -> Option<i64> + 'static,
+{
+    let (a, b) = (reader(a), reader(b));
+    Rc::new(move |m| {
+        let (x, y) = (a(m)?, b(m)?);
+        let result = op(x, y).ok_or_else(|| format!("overflow at pc {}: {} and {}", m.pc, x, y))?;
+        let addr = m.write(c, result)?;
+        m.pc += 4;
+        Ok(Some(addr))
+    })
+}
+
+fn compile_jump<P>(a: Param, target: Param, predicate: P) -> Op
+where
+    P: Fn(i64) -> bool + 'static,
+{
+    let (a, target) = (reader(a), reader(target));
+    Rc::new(move |m| {
+        if predicate(a(m)?) {
+            let target = target(m)?;
+            m.jump(target, 3)?;
+        } else {
+            m.pc += 3;
+        }
+        Ok(None)
+    })
+}
+
+impl Engine for Closures {
+    type Compiled = Op;
+
+    fn compile(instruction: Instruction) -> Op {
+        let flag = |condition: bool| Some(if condition { 1 } else { 0 });
+        match instruction {
+            Instruction::Add(a, b, c) => compile_arithmetic(a, b, c, i64::checked_add),
+            Instruction::Mult(a, b, c) => compile_arithmetic(a, b, c, i64::checked_mul),
+            Instruction::LessThan(a, b, c) => compile_arithmetic(a, b, c, move |x, y| flag(x < y)),
+            Instruction::Equals(a, b, c) => compile_arithmetic(a, b, c, move |x, y| flag(x == y)),
+            Instruction::Input(a) => Rc::new(move |m| m.input(a)),
+            Instruction::Output(a) => {
+                let a = reader(a);
+                Rc::new(move |m| {
+                    let data = a(m)?;
+                    m.output.push_back(data);
+                    m.pc += 2;
+                    Ok(None)
+                })
+            }
+            Instruction::JumpIfTrue(a, b) => compile_jump(a, b, |data| data != 0),
+            Instruction::JumpIfFalse(a, b) => compile_jump(a, b, |data| data == 0),
+            Instruction::SetBase(a) => {
+                let a = reader(a);
+                Rc::new(move |m| {
+                    let offset = a(m)?;
+                    m.base = m
+                        .base
+                        .checked_add(offset)
+                        .ok_or("relative base overflowed")?;
+                    m.pc += 2;
+                    Ok(None)
+                })
+            }
+            Instruction::Halt => Rc::new(|m| {
+                m.halt();
+                Ok(None)
+            }),
+        }
+    }
+
+    fn execute(op: &Op, machine: &mut Machine) -> Result<Option<usize>, String> {
+        op(machine)
+    }
+}
+
+pub struct Interpreter<E: Engine> {
+    machine: Machine,
+    cache: Vec<Option<E::Compiled>>,
+}
+
+pub type Decoded = Interpreter<DecodeOnce>;
+pub type Threaded = Interpreter<Closures>;
+
+impl<E: Engine> Interpreter<E> {
+    pub fn new(memory: &Memory) -> Self {
+        let machine = Machine::new(memory);
+        let cache = machine.memory.iter().map(|_| None).collect();
+        Interpreter { machine, cache }
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.machine.halted
+    }
+
+    pub fn get_pc(&self) -> usize {
+        self.machine.pc
+    }
+
+    /// Memory including the zero padding after the program.
+    pub fn get_memory(&self) -> &[i64] {
+        &self.machine.memory
+    }
+
+    pub fn set_input(&mut self, input: i64) {
+        self.machine.input.push_back(input);
+    }
+
+    pub fn get_output(&mut self) -> Option<i64> {
+        self.machine.output.pop_front()
+    }
+
+    /// Runs one instruction. After an error the interpreter is in whatever
+    /// state the failing instruction left it, and should not be stepped
+    /// again.
+    pub fn step(&mut self) -> Result<(), String> {
+        if self.machine.halted {
+            return Ok(());
+        }
+
+        let pc = self.machine.pc;
+        if !matches!(self.cache.get(pc), Some(Some(_))) {
+            let instruction = decode(&self.machine.memory, pc)?;
+            self.cache[pc] = Some(E::compile(instruction));
+        }
+        let compiled = self.cache[pc]
+            .as_ref()
+            .expect("Instruction was just decoded");
+
+        if let Some(addr) = E::execute(compiled, &mut self.machine)? {
+            let first = addr.saturating_sub(MAX_LEN - 1);
+            for entry in self.cache[first..=addr].iter_mut() {
+                *entry = None;
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs until the program halts, fails, or needs input it doesn't have.
+    pub fn run(&mut self) -> Result<(), String> {
+        while !self.machine.halted {
+            let pc = self.machine.pc;
+            self.step()?;
+            if self.machine.pc == pc && !self.machine.halted {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn expect_program(initial_mem: Memory, expected_mem: Memory) {
+        let mut decoded = Decoded::new(&initial_mem);
+        decoded.run().unwrap();
+        assert_eq!(
+            &decoded.get_memory()[..expected_mem.len()],
+            &expected_mem[..]
+        );
+
+        let mut threaded = Threaded::new(&initial_mem);
+        threaded.run().unwrap();
+        assert_eq!(
+            &threaded.get_memory()[..expected_mem.len()],
+            &expected_mem[..]
+        );
+    }
+
+    fn outputs<E: Engine>(program: &Memory, input: i64) -> Vec<i64> {
+        let mut cpu = Interpreter::<E>::new(program);
+        cpu.set_input(input);
+        cpu.run().unwrap();
+        let mut outputs = Vec::new();
+        while let Some(data) = cpu.get_output() {
+            outputs.push(data);
+        }
+        outputs
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(
+            decode(&[1002, 4, 3, 4], 0),
+            Ok(Instruction::Mult(
+                Param::Position(4),
+                Param::Immediate(3),
+                Param::Position(4)
+            ))
+        );
+        assert_eq!(
+            decode(&[203, 7], 0),
+            Ok(Instruction::Input(Param::Relative(7)))
+        );
+        assert!(decode(&[42, 0, 0, 0], 0).is_err());
+        assert!(decode(&[301, 0, 0, 0], 0).is_err());
+        assert!(decode(&[1, 0], 0).is_err());
+    }
+
+    #[test]
+    fn test_day2_programs() {
+        expect_program(vec![1, 0, 0, 0, 99], vec![2, 0, 0, 0, 99]);
+        expect_program(vec![2, 3, 0, 3, 99], vec![2, 3, 0, 6, 99]);
+        expect_program(vec![2, 4, 4, 5, 99, 0], vec![2, 4, 4, 5, 99, 9801]);
+        expect_program(
+            vec![1, 1, 1, 4, 99, 5, 6, 0, 99],
+            vec![30, 1, 1, 4, 2, 5, 6, 0, 99],
+        );
+    }
+
+    fn run_memory<E: Engine>(program: &Memory) -> Vec<i64> {
+        let mut cpu = Interpreter::<E>::new(program);
+        cpu.run().unwrap();
+        cpu.get_memory()[..program.len()].to_vec()
+    }
+
+    #[test]
+    fn test_self_modifying() {
+        // Each pass adds one more to the counter at 20 than the last, by
+        // rewriting the immediate of the cached add at 0, until it reaches 7.
+        let program = vec![
+            1001, 20, 1, 20, 1001, 2, 1, 2, 1007, 20, 7, 21, 1005, 21, 0, 99, 0, 0, 0, 0, 0, 0,
+        ];
+        for memory in [
+            run_memory::<DecodeOnce>(&program),
+            run_memory::<Closures>(&program),
+        ]
+        .iter()
+        {
+            assert_eq!(memory[20], 10);
+            assert_eq!(memory[2], 5);
+        }
+    }
+
+    #[test]
+    fn test_compare_and_jump() {
+        // Outputs 999 below 8, 1000 at 8 and 1001 above.
+        let program = vec![
+            3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0,
+            0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4,
+            20, 1105, 1, 46, 98, 99,
+        ];
+        for &(input, expected) in [(7, 999), (8, 1000), (9, 1001)].iter() {
+            assert_eq!(outputs::<DecodeOnce>(&program, input), vec![expected]);
+            assert_eq!(outputs::<Closures>(&program, input), vec![expected]);
+        }
+    }
+
+    #[test]
+    fn test_relative() {
+        let quine = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        assert_eq!(outputs::<DecodeOnce>(&quine, 0), quine);
+        assert_eq!(outputs::<Closures>(&quine, 0), quine);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut cpu = Threaded::new(&vec![1101, i64::MAX, 1, 0, 99]);
+        assert!(cpu.step().is_err());
+        let mut cpu = Decoded::new(&vec![42, 0, 0, 0]);
+        assert!(cpu.step().is_err());
+        let mut cpu = Decoded::new(&vec![1, -1, 0, 0, 99]);
+        assert!(cpu.step().is_err());
+    }
+
+    #[test]
+    fn test_stalls_for_input() {
+        let mut cpu = Decoded::new(&vec![3, 5, 4, 5, 99, 0]);
+        cpu.run().unwrap();
+        assert_eq!(cpu.get_pc(), 0);
+        cpu.set_input(7);
+        cpu.run().unwrap();
+        assert!(cpu.is_halted());
+        assert_eq!(cpu.get_output(), Some(7));
+    }
+}
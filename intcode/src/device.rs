@@ -0,0 +1,458 @@
+//! Peripherals for the Intcode computer. A `Bus` owns the computer and moves
+//! values between it and whatever devices are plugged in, so a puzzle only
+//! has to describe its peripheral instead of hand-wiring `set_input` and
+//! `get_output` calls.
+
+use crate::computer::{Computer, VmError};
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Write};
+
+pub trait Device {
+    /// Receives every value the computer outputs.
+    fn consume(&mut self, data: i64);
+
+    /// Offers the next input value when the computer is waiting for one.
+    fn produce(&mut self) -> Option<i64> {
+        None
+    }
+
+    /// Sees every input value the computer receives, whichever device
+    /// produced it.
+    fn observe_input(&mut self, _data: i64) {}
+
+    /// Runs once at the end of every bus tick.
+    fn tick(&mut self) {}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BusState {
+    /// The computer took an input and can keep going.
+    Running,
+    Halted,
+    /// The computer wants input and no device had any.
+    Starved,
+    Failed(VmError),
+}
+
+pub struct Bus {
+    cpu: Computer,
+}
+
+impl Bus {
+    pub fn new(cpu: Computer) -> Self {
+        Bus { cpu }
+    }
+
+    pub fn cpu(&self) -> &Computer {
+        &self.cpu
+    }
+
+    pub fn cpu_mut(&mut self) -> &mut Computer {
+        &mut self.cpu
+    }
+
+    pub fn into_cpu(self) -> Computer {
+        self.cpu
+    }
+
+    /// Runs the computer until it stops, hands its output to every device,
+    /// then feeds it at most one input from the first device that has one.
+    /// Feeding one value at a time lets devices react to the output that
+    /// came before it, which is how the joystick and droid puzzles work.
+    pub fn tick(&mut self, devices: &mut [&mut dyn Device]) -> BusState {
+        self.cpu.run();
+
+        while let Some(data) = self.cpu.get_output() {
+            for device in devices.iter_mut() {
+                device.consume(data);
+            }
+        }
+
+        let state = if let Some(error) = self.cpu.get_error() {
+            BusState::Failed(error.clone())
+        } else if self.cpu.is_halted() {
+            BusState::Halted
+        } else {
+            match devices.iter_mut().find_map(|device| device.produce()) {
+                Some(data) => {
+                    self.cpu.set_input(data);
+                    for device in devices.iter_mut() {
+                        device.observe_input(data);
+                    }
+                    BusState::Running
+                }
+                None => BusState::Starved,
+            }
+        };
+
+        for device in devices.iter_mut() {
+            device.tick();
+        }
+
+        state
+    }
+
+    /// Ticks until the computer halts, fails, or starves for input.
+    pub fn run(&mut self, devices: &mut [&mut dyn Device]) -> BusState {
+        loop {
+            let state = self.tick(devices);
+            if state != BusState::Running {
+                return state;
+            }
+        }
+    }
+}
+
+/// Answers every input request with the same value, like a joystick left
+/// in neutral.
+pub struct ConstantInput {
+    data: i64,
+}
+
+impl ConstantInput {
+    pub fn new(data: i64) -> Self {
+        ConstantInput { data }
+    }
+}
+
+impl Device for ConstantInput {
+    fn consume(&mut self, _data: i64) {}
+
+    fn produce(&mut self) -> Option<i64> {
+        Some(self.data)
+    }
+}
+
+/// Plays back a fixed list of inputs, then runs dry.
+pub struct ScriptedInput {
+    script: VecDeque<i64>,
+}
+
+impl ScriptedInput {
+    pub fn new(script: &[i64]) -> Self {
+        ScriptedInput {
+            script: script.iter().copied().collect(),
+        }
+    }
+
+    /// Each line becomes ASCII codes followed by a newline, the way the
+    /// vacuum robot and springdroid expect their commands.
+    pub fn from_lines(lines: &[&str]) -> Self {
+        let script = lines
+            .iter()
+            .flat_map(|line| line.chars().chain(Some('\n')))
+            .map(|c| c as i64)
+            .collect::<Vec<_>>();
+        ScriptedInput::new(&script)
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.script.len()
+    }
+}
+
+impl Device for ScriptedInput {
+    fn consume(&mut self, _data: i64) {}
+
+    fn produce(&mut self) -> Option<i64> {
+        self.script.pop_front()
+    }
+}
+
+/// Collects ASCII output as text and keeps anything outside the ASCII
+/// range, such as the dust total or hull damage, as a plain value. Lines
+/// queued with `send_line` are typed back into the computer.
+pub struct AsciiTerminal {
+    text: String,
+    values: Vec<i64>,
+    pending: VecDeque<i64>,
+    echo: bool,
+}
+
+impl AsciiTerminal {
+    pub fn new() -> Self {
+        AsciiTerminal {
+            text: String::new(),
+            values: Vec::new(),
+            pending: VecDeque::new(),
+            echo: false,
+        }
+    }
+
+    /// Also prints output to stdout as it arrives.
+    pub fn echo(mut self, echo: bool) -> Self {
+        self.echo = echo;
+        self
+    }
+
+    pub fn send_line(&mut self, line: &str) {
+        for c in line.chars() {
+            self.pending.push_back(c as i64);
+        }
+        self.pending.push_back('\n' as i64);
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn take_text(&mut self) -> String {
+        std::mem::take(&mut self.text)
+    }
+
+    pub fn values(&self) -> &[i64] {
+        &self.values
+    }
+}
+
+impl Default for AsciiTerminal {
+    fn default() -> Self {
+        AsciiTerminal::new()
+    }
+}
+
+impl Device for AsciiTerminal {
+    fn consume(&mut self, data: i64) {
+        if (0..128).contains(&data) {
+            let c = data as u8 as char;
+            self.text.push(c);
+            if self.echo {
+                print!("{}", c);
+            }
+        } else {
+            self.values.push(data);
+        }
+    }
+
+    fn produce(&mut self) -> Option<i64> {
+        self.pending.pop_front()
+    }
+
+    fn tick(&mut self) {
+        if self.echo {
+            io::stdout().flush().unwrap();
+        }
+    }
+}
+
+/// Decodes output as `(x, y, tile)` triples into a sparse grid. The
+/// `(-1, 0)` position is the arcade cabinet's segment display, so it is
+/// kept as the score rather than as a tile.
+pub struct TileScreen {
+    tiles: HashMap<(i64, i64), i64>,
+    score: Option<i64>,
+    partial: Vec<i64>,
+}
+
+impl TileScreen {
+    pub fn new() -> Self {
+        TileScreen {
+            tiles: HashMap::new(),
+            score: None,
+            partial: Vec::new(),
+        }
+    }
+
+    pub fn get(&self, x: i64, y: i64) -> Option<i64> {
+        self.tiles.get(&(x, y)).copied()
+    }
+
+    pub fn score(&self) -> Option<i64> {
+        self.score
+    }
+
+    pub fn count(&self, tile: i64) -> usize {
+        self.tiles.values().filter(|&&t| t == tile).count()
+    }
+
+    /// Finds a tile that should appear once, like the ball or paddle.
+    pub fn find(&self, tile: i64) -> Option<(i64, i64)> {
+        self.tiles
+            .iter()
+            .find(|(_, &t)| t == tile)
+            .map(|(&point, _)| point)
+    }
+
+    /// Draws the grid from (0, 0) to the largest coordinates seen, with
+    /// `to_char` choosing each cell's glyph.
+    pub fn render<F>(&self, to_char: F) -> String
+    where
+        F: Fn(i64) -> char,
+    {
+        let x_max = self.tiles.keys().map(|&(x, _)| x).max().unwrap_or(0);
+        let y_max = self.tiles.keys().map(|&(_, y)| y).max().unwrap_or(0);
+
+        let mut output = String::new();
+        for y in 0..=y_max {
+            for x in 0..=x_max {
+                output.push(to_char(self.get(x, y).unwrap_or(0)));
+            }
+            output.push('\n');
+        }
+        output
+    }
+}
+
+impl Default for TileScreen {
+    fn default() -> Self {
+        TileScreen::new()
+    }
+}
+
+impl Device for TileScreen {
+    fn consume(&mut self, data: i64) {
+        self.partial.push(data);
+        if self.partial.len() == 3 {
+            match (self.partial[0], self.partial[1], self.partial[2]) {
+                (-1, 0, score) => self.score = Some(score),
+                (x, y, tile) => {
+                    self.tiles.insert((x, y), tile);
+                }
+            }
+            self.partial.clear();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Input(i64),
+    Output(i64),
+}
+
+/// Records all traffic on the bus in order without touching it.
+pub struct Tap {
+    events: Vec<Event>,
+}
+
+impl Tap {
+    pub fn new() -> Self {
+        Tap { events: Vec::new() }
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    pub fn inputs(&self) -> Vec<i64> {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Input(data) => Some(*data),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn outputs(&self) -> Vec<i64> {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Output(data) => Some(*data),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Default for Tap {
+    fn default() -> Self {
+        Tap::new()
+    }
+}
+
+impl Device for Tap {
+    fn consume(&mut self, data: i64) {
+        self.events.push(Event::Output(data));
+    }
+
+    fn observe_input(&mut self, data: i64) {
+        self.events.push(Event::Input(data));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reads a value and outputs it doubled, until it reads a zero.
+    fn doubler() -> Computer {
+        Computer::new(&vec![
+            3, 15, 1006, 15, 14, 1002, 15, 2, 16, 4, 16, 1105, 1, 0, 99, 0, 0,
+        ])
+    }
+
+    #[test]
+    fn test_scripted_input_and_tap() {
+        let mut script = ScriptedInput::new(&[3, 5, 0]);
+        let mut tap = Tap::new();
+        let state = Bus::new(doubler()).run(&mut [&mut script, &mut tap]);
+        assert_eq!(state, BusState::Halted);
+        assert_eq!(tap.inputs(), vec![3, 5, 0]);
+        assert_eq!(tap.outputs(), vec![6, 10]);
+        assert_eq!(
+            tap.events(),
+            &[
+                Event::Input(3),
+                Event::Output(6),
+                Event::Input(5),
+                Event::Output(10),
+                Event::Input(0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_starved() {
+        let mut script = ScriptedInput::new(&[4]);
+        let mut tap = Tap::new();
+        let mut bus = Bus::new(doubler());
+        assert_eq!(bus.run(&mut [&mut script, &mut tap]), BusState::Starved);
+        assert_eq!(tap.outputs(), vec![8]);
+        assert!(bus.cpu().is_blocked());
+    }
+
+    #[test]
+    fn test_first_device_with_input_wins() {
+        let mut script = ScriptedInput::new(&[1, 0]);
+        let mut constant = ConstantInput::new(7);
+        let mut tap = Tap::new();
+        Bus::new(doubler()).tick(&mut [&mut script, &mut constant, &mut tap]);
+        assert_eq!(tap.inputs(), vec![1]);
+        assert_eq!(script.remaining(), 1);
+    }
+
+    #[test]
+    fn test_ascii_terminal() {
+        // Prints "Hi\n", then a large value, then echoes one typed line.
+        let mem = vec![
+            104, 72, 104, 105, 104, 10, 104, 1000, 3, 21, 4, 21, 3, 21, 4, 21, 3, 21, 4, 21, 99, 0,
+        ];
+        let mut terminal = AsciiTerminal::new();
+        terminal.send_line("ok");
+        let state = Bus::new(Computer::new(&mem)).run(&mut [&mut terminal]);
+        assert_eq!(state, BusState::Halted);
+        assert_eq!(terminal.text(), "Hi\nok\n");
+        assert_eq!(terminal.values(), &[1000]);
+    }
+
+    #[test]
+    fn test_tile_screen() {
+        let mem = vec![
+            104, 1, 104, 2, 104, 3, 104, 6, 104, 5, 104, 4, 104, -1, 104, 0, 104, 12345, 104, 1,
+            104, 2, 104, 2, 99,
+        ];
+        let mut screen = TileScreen::new();
+        Bus::new(Computer::new(&mem)).run(&mut [&mut screen]);
+        assert_eq!(screen.get(1, 2), Some(2));
+        assert_eq!(screen.find(4), Some((6, 5)));
+        assert_eq!(screen.count(2), 1);
+        assert_eq!(screen.score(), Some(12345));
+        assert_eq!(screen.get(-1, 0), None);
+
+        let frame = screen.render(|tile| if tile == 0 { '.' } else { '#' });
+        assert_eq!(frame.lines().count(), 6);
+        assert_eq!(frame.lines().nth(5), Some("......#"));
+    }
+}
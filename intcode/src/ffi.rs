@@ -0,0 +1,304 @@
+//! C API over `Computer`. Every function takes or returns an opaque handle.
+//! `build.rs` turns the declarations in this file into `include/intcode.h`,
+//! so stick to the types it knows about and keep each constant as a plain
+//! one-line `pub const`.
+//!
+//! Safety is the same for every function, so it is stated once here: handles
+//! must come from this API and must not be used after being freed, and data
+//! pointers must be valid for the length given. Null handles are rejected.
+//!
+//! A panic must never unwind into C, so every function runs its body under
+//! `guard`, and a panic comes back as `INTCODE_ERROR` or a null handle.
+#![allow(clippy::missing_safety_doc)]
+
+use crate::backends::catch_panic;
+use crate::computer::Computer;
+
+use std::slice;
+
+/// The computer can keep running.
+pub const INTCODE_RUNNING: i32 = 0;
+/// At least one output value is waiting to be popped.
+pub const INTCODE_OUTPUT: i32 = 1;
+/// The computer is waiting for input.
+pub const INTCODE_BLOCKED: i32 = 2;
+pub const INTCODE_HALTED: i32 = 3;
+/// The computer stopped on an error such as an overflow or an unknown op
+/// code.
+pub const INTCODE_ERROR: i32 = 4;
+/// A null handle, or an address outside memory.
+pub const INTCODE_BAD_ARGUMENT: i32 = -1;
+
+pub struct IntcodeVm {
+    cpu: Computer,
+    /// Set when the interpreter panicked. The computer is left however the
+    /// failing instruction found it, so it is never stepped again.
+    panicked: bool,
+}
+
+pub struct IntcodeSnapshot {
+    cpu: Computer,
+}
+
+fn status(vm: &IntcodeVm) -> i32 {
+    let cpu = &vm.cpu;
+    if vm.panicked || cpu.get_error().is_some() {
+        INTCODE_ERROR
+    } else if cpu.has_output() {
+        INTCODE_OUTPUT
+    } else if cpu.is_halted() {
+        INTCODE_HALTED
+    } else if cpu.is_blocked() {
+        INTCODE_BLOCKED
+    } else {
+        INTCODE_RUNNING
+    }
+}
+
+fn step(vm: &mut IntcodeVm) {
+    if !vm.panicked {
+        let cpu = &mut vm.cpu;
+        vm.panicked = catch_panic(|| cpu.step()).is_err();
+    }
+}
+
+/// Runs `body`, returning `fallback` if it panics.
+fn guard<T, F>(fallback: T, body: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    catch_panic(body).unwrap_or(fallback)
+}
+
+/// Creates a computer from `len` words of program memory. Free it with
+/// `intcode_free`.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_new(memory: *const i64, len: usize) -> *mut IntcodeVm {
+    guard(std::ptr::null_mut(), || {
+        if memory.is_null() {
+            return std::ptr::null_mut();
+        }
+        let program = slice::from_raw_parts(memory, len).to_vec();
+        Box::into_raw(Box::new(IntcodeVm {
+            cpu: Computer::new(&program),
+            panicked: false,
+        }))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn intcode_free(vm: *mut IntcodeVm) {
+    guard((), || {
+        if !vm.is_null() {
+            drop(Box::from_raw(vm));
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn intcode_push_input(vm: *mut IntcodeVm, data: i64) -> i32 {
+    guard(INTCODE_ERROR, || match vm.as_mut() {
+        Some(vm) => {
+            vm.cpu.set_input(data);
+            INTCODE_RUNNING
+        }
+        None => INTCODE_BAD_ARGUMENT,
+    })
+}
+
+/// Pops the oldest output value into `data`. Returns 1 if there was one and
+/// 0 if the queue was empty.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_pop_output(vm: *mut IntcodeVm, data: *mut i64) -> i32 {
+    guard(INTCODE_ERROR, || match (vm.as_mut(), data.as_mut()) {
+        (Some(vm), Some(data)) => match vm.cpu.get_output() {
+            Some(value) => {
+                *data = value;
+                1
+            }
+            None => 0,
+        },
+        _ => INTCODE_BAD_ARGUMENT,
+    })
+}
+
+/// Executes one instruction and returns the resulting status.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_step(vm: *mut IntcodeVm) -> i32 {
+    guard(INTCODE_ERROR, || match vm.as_mut() {
+        Some(vm) => {
+            step(vm);
+            status(vm)
+        }
+        None => INTCODE_BAD_ARGUMENT,
+    })
+}
+
+/// Runs until there is output to pop, the computer needs input, halts, or
+/// fails. Never returns `INTCODE_RUNNING`.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_run(vm: *mut IntcodeVm) -> i32 {
+    guard(INTCODE_ERROR, || match vm.as_mut() {
+        Some(vm) => loop {
+            let status = status(vm);
+            if status != INTCODE_RUNNING {
+                return status;
+            }
+            step(vm);
+        },
+        None => INTCODE_BAD_ARGUMENT,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn intcode_status(vm: *const IntcodeVm) -> i32 {
+    guard(INTCODE_ERROR, || match vm.as_ref() {
+        Some(vm) => status(vm),
+        None => INTCODE_BAD_ARGUMENT,
+    })
+}
+
+/// Returns the program counter, or -1 for a null handle.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_pc(vm: *const IntcodeVm) -> i64 {
+    guard(-1, || match vm.as_ref() {
+        Some(vm) => vm.cpu.get_pc() as i64,
+        None => -1,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn intcode_read_memory(
+    vm: *const IntcodeVm,
+    addr: usize,
+    data: *mut i64,
+) -> i32 {
+    guard(INTCODE_ERROR, || match (vm.as_ref(), data.as_mut()) {
+        (Some(vm), Some(data)) => match vm.cpu.peek(addr) {
+            Some(value) => {
+                *data = value;
+                INTCODE_RUNNING
+            }
+            None => INTCODE_BAD_ARGUMENT,
+        },
+        _ => INTCODE_BAD_ARGUMENT,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn intcode_write_memory(vm: *mut IntcodeVm, addr: usize, data: i64) -> i32 {
+    guard(INTCODE_ERROR, || match vm.as_mut() {
+        Some(vm) => {
+            if vm.cpu.poke(addr, data) {
+                INTCODE_RUNNING
+            } else {
+                INTCODE_BAD_ARGUMENT
+            }
+        }
+        None => INTCODE_BAD_ARGUMENT,
+    })
+}
+
+/// Copies the computer's whole state. Free it with `intcode_snapshot_free`.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_take_snapshot(vm: *const IntcodeVm) -> *mut IntcodeSnapshot {
+    guard(std::ptr::null_mut(), || match vm.as_ref() {
+        Some(vm) => Box::into_raw(Box::new(IntcodeSnapshot {
+            cpu: vm.cpu.clone(),
+        })),
+        None => std::ptr::null_mut(),
+    })
+}
+
+/// Puts the computer back in the snapshot's state. The snapshot stays valid
+/// and can be restored again, which also recovers a computer that failed.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_restore(
+    vm: *mut IntcodeVm,
+    snapshot: *const IntcodeSnapshot,
+) -> i32 {
+    guard(INTCODE_ERROR, || match (vm.as_mut(), snapshot.as_ref()) {
+        (Some(vm), Some(snapshot)) => {
+            vm.cpu = snapshot.cpu.clone();
+            vm.panicked = false;
+            status(vm)
+        }
+        _ => INTCODE_BAD_ARGUMENT,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn intcode_snapshot_free(snapshot: *mut IntcodeSnapshot) {
+    guard((), || {
+        if !snapshot.is_null() {
+            drop(Box::from_raw(snapshot));
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_is_current() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/intcode.h"));
+        let shipped = include_str!("../include/intcode.h");
+        assert!(
+            generated == shipped,
+            "include/intcode.h is stale, copy it from {}/intcode.h",
+            env!("OUT_DIR")
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let program = vec![3, 9, 1002, 9, 3, 9, 4, 9, 99, 0];
+        unsafe {
+            let vm = intcode_new(program.as_ptr(), program.len());
+            assert_eq!(intcode_run(vm), INTCODE_BLOCKED);
+            let snapshot = intcode_take_snapshot(vm);
+
+            intcode_push_input(vm, 14);
+            assert_eq!(intcode_run(vm), INTCODE_OUTPUT);
+            let mut data = 0;
+            assert_eq!(intcode_pop_output(vm, &mut data), 1);
+            assert_eq!(data, 42);
+            assert_eq!(intcode_run(vm), INTCODE_HALTED);
+
+            assert_eq!(intcode_restore(vm, snapshot), INTCODE_BLOCKED);
+            assert_eq!(intcode_write_memory(vm, 4, 5), INTCODE_RUNNING);
+            intcode_push_input(vm, 2);
+            intcode_run(vm);
+            intcode_pop_output(vm, &mut data);
+            assert_eq!(data, 10);
+
+            assert_eq!(intcode_read_memory(vm, 9, &mut data), INTCODE_RUNNING);
+            assert_eq!(data, 10);
+            assert_eq!(
+                intcode_read_memory(vm, usize::MAX, &mut data),
+                INTCODE_BAD_ARGUMENT
+            );
+
+            intcode_snapshot_free(snapshot);
+            intcode_free(vm);
+            assert_eq!(intcode_run(std::ptr::null_mut()), INTCODE_BAD_ARGUMENT);
+        }
+    }
+
+    #[test]
+    fn test_panic_is_an_error() {
+        // Reads far past the end of memory, which panics.
+        let program = [4, 1_000_000, 99];
+        unsafe {
+            let vm = intcode_new(program.as_ptr(), program.len());
+            let snapshot = intcode_take_snapshot(vm);
+            assert_eq!(intcode_run(vm), INTCODE_ERROR);
+            assert_eq!(intcode_step(vm), INTCODE_ERROR);
+            assert_eq!(intcode_status(vm), INTCODE_ERROR);
+            assert_eq!(intcode_restore(vm, snapshot), INTCODE_RUNNING);
+            intcode_snapshot_free(snapshot);
+            intcode_free(vm);
+        }
+    }
+}
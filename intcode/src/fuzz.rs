@@ -0,0 +1,495 @@
+//! Differential fuzzing for the Intcode interpreters in `backends`.
+//!
+//! Programs are generated structurally rather than as raw memory: code only
+//! ever writes to a data block placed after the final halt, and jumps only
+//! land on instruction boundaries. That keeps every generated program valid,
+//! so any disagreement between backends is a real semantic difference and
+//! not an artifact of executing garbage.
+
+use crate::backends::{Backend, Outcome, Profile};
+
+use std::fmt;
+
+/// xorshift64*, which is plenty for picking opcodes and keeps the crate free
+/// of dependencies.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng {
+            state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    pub fn range(&mut self, lo: i64, hi: i64) -> i64 {
+        lo + (self.next_u64() % (hi - lo + 1) as u64) as i64
+    }
+}
+
+/// An input parameter: either a data cell read in position mode or a value
+/// in immediate mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Param {
+    Data(usize),
+    Immediate(i64),
+}
+
+/// One instruction. Write targets are data cell indices and jump targets
+/// are instruction indices, where `ops.len()` means the final halt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Add(Param, Param, usize),
+    Mult(Param, Param, usize),
+    Input(usize),
+    Output(Param),
+    JumpIfTrue(Param, usize),
+    JumpIfFalse(Param, usize),
+    LessThan(Param, Param, usize),
+    Equals(Param, Param, usize),
+}
+
+impl Op {
+    fn len(&self) -> usize {
+        match self {
+            Op::Input(_) | Op::Output(_) => 2,
+            Op::JumpIfTrue(_, _) | Op::JumpIfFalse(_, _) => 3,
+            _ => 4,
+        }
+    }
+
+    fn params(&self) -> Vec<Param> {
+        let mut op = *self;
+        op.params_mut().into_iter().map(|param| *param).collect()
+    }
+
+    fn params_mut(&mut self) -> Vec<&mut Param> {
+        match self {
+            Op::Add(a, b, _) | Op::Mult(a, b, _) | Op::LessThan(a, b, _) | Op::Equals(a, b, _) => {
+                vec![a, b]
+            }
+            Op::Output(a) | Op::JumpIfTrue(a, _) | Op::JumpIfFalse(a, _) => vec![a],
+            Op::Input(_) => vec![],
+        }
+    }
+
+    fn target_mut(&mut self) -> Option<&mut usize> {
+        match self {
+            Op::JumpIfTrue(_, target) | Op::JumpIfFalse(_, target) => Some(target),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    pub ops: Vec<Op>,
+    pub data: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FuzzOptions {
+    pub profile: Profile,
+    pub max_ops: usize,
+    pub max_data: usize,
+    pub max_value: i64,
+    pub max_steps: usize,
+}
+
+impl FuzzOptions {
+    pub fn new(profile: Profile) -> Self {
+        FuzzOptions {
+            profile,
+            max_ops: 12,
+            max_data: 4,
+            max_value: 1000,
+            max_steps: 1000,
+        }
+    }
+}
+
+impl Program {
+    pub fn generate(rng: &mut Rng, options: &FuzzOptions) -> Self {
+        let num_data = 1 + rng.below(options.max_data);
+        let num_ops = 1 + rng.below(options.max_ops);
+        let min_value = match options.profile {
+            Profile::Day2 => 0,
+            Profile::Day5 => -options.max_value,
+        };
+
+        let data = (0..num_data)
+            .map(|_| rng.range(min_value, options.max_value))
+            .collect::<Vec<_>>();
+
+        let ops = (0..num_ops)
+            .map(|_| {
+                let param = |rng: &mut Rng| {
+                    if options.profile == Profile::Day5 && rng.below(2) == 0 {
+                        Param::Immediate(rng.range(min_value, options.max_value))
+                    } else {
+                        Param::Data(rng.below(num_data))
+                    }
+                };
+                let choices = match options.profile {
+                    Profile::Day2 => 2,
+                    Profile::Day5 => 8,
+                };
+                match rng.below(choices) {
+                    0 => Op::Add(param(rng), param(rng), rng.below(num_data)),
+                    1 => Op::Mult(param(rng), param(rng), rng.below(num_data)),
+                    2 => Op::Input(rng.below(num_data)),
+                    3 => Op::Output(param(rng)),
+                    4 => Op::JumpIfTrue(param(rng), rng.below(num_ops + 1)),
+                    5 => Op::JumpIfFalse(param(rng), rng.below(num_ops + 1)),
+                    6 => Op::LessThan(param(rng), param(rng), rng.below(num_data)),
+                    _ => Op::Equals(param(rng), param(rng), rng.below(num_data)),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Program { ops, data }
+    }
+
+    fn addresses(&self) -> Vec<i64> {
+        let mut addr = 0;
+        let mut addresses = Vec::new();
+        for op in self.ops.iter() {
+            addresses.push(addr);
+            addr += op.len() as i64;
+        }
+        addresses.push(addr);
+        addresses
+    }
+
+    /// Lays the program out as memory: the instructions, a halt, then the
+    /// data cells.
+    pub fn encode(&self) -> Vec<i64> {
+        let addresses = self.addresses();
+        let data_start = addresses[self.ops.len()] + 1;
+
+        let mut memory = Vec::new();
+        let mut emit = |op_code: i64, params: &[Param], extra: &[i64]| {
+            let modes = params.iter().enumerate().fold(0, |acc, (i, param)| match param {
+                Param::Immediate(_) => acc + 10_i64.pow(i as u32 + 2),
+                Param::Data(_) => acc,
+            });
+            memory.push(op_code + modes);
+            for param in params.iter() {
+                memory.push(match *param {
+                    Param::Data(k) => data_start + k as i64,
+                    Param::Immediate(value) => value,
+                });
+            }
+            memory.extend_from_slice(extra);
+        };
+
+        for op in self.ops.iter() {
+            let cell = |k: usize| data_start + k as i64;
+            match *op {
+                Op::Add(a, b, k) => emit(1, &[a, b], &[cell(k)]),
+                Op::Mult(a, b, k) => emit(2, &[a, b], &[cell(k)]),
+                Op::Input(k) => emit(3, &[], &[cell(k)]),
+                Op::Output(a) => emit(4, &[a], &[]),
+                Op::JumpIfTrue(a, t) => emit(5, &[a, Param::Immediate(addresses[t])], &[]),
+                Op::JumpIfFalse(a, t) => emit(6, &[a, Param::Immediate(addresses[t])], &[]),
+                Op::LessThan(a, b, k) => emit(7, &[a, b], &[cell(k)]),
+                Op::Equals(a, b, k) => emit(8, &[a, b], &[cell(k)]),
+            }
+        }
+        memory.push(99);
+        memory.extend_from_slice(&self.data);
+        memory
+    }
+
+    fn without_op(&self, index: usize) -> Program {
+        let mut ops = self.ops.clone();
+        ops.remove(index);
+        for op in ops.iter_mut() {
+            if let Some(target) = op.target_mut() {
+                if *target > index {
+                    *target -= 1;
+                }
+            }
+        }
+        Program {
+            ops,
+            data: self.data.clone(),
+        }
+    }
+
+    /// Every program one simplification step away from this one, roughly
+    /// in order of how much each step shrinks the program.
+    fn shrink_candidates(&self) -> Vec<Program> {
+        let mut candidates = (0..self.ops.len())
+            .rev()
+            .map(|index| self.without_op(index))
+            .collect::<Vec<_>>();
+
+        for (k, &value) in self.data.iter().enumerate() {
+            for smaller in smaller_values(value) {
+                let mut candidate = self.clone();
+                candidate.data[k] = smaller;
+                candidates.push(candidate);
+            }
+        }
+
+        for (index, op) in self.ops.iter().enumerate() {
+            for (p, param) in op.params().into_iter().enumerate() {
+                if let Param::Immediate(value) = param {
+                    for smaller in smaller_values(value) {
+                        let mut candidate = self.clone();
+                        *candidate.ops[index].params_mut()[p] = Param::Immediate(smaller);
+                        candidates.push(candidate);
+                    }
+                }
+            }
+        }
+
+        candidates
+    }
+}
+
+fn smaller_values(value: i64) -> Vec<i64> {
+    let mut values = vec![0, 1, value / 2];
+    if value < 0 {
+        values.push(-value);
+    }
+    values.retain(|&smaller| smaller.abs() < value.abs() || (smaller == -value && value < 0));
+    values.dedup();
+    values
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let memory = self
+            .encode()
+            .iter()
+            .map(|data| data.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "{}", memory.join(","))
+    }
+}
+
+/// Crashes count as agreement whatever the panic message says, since each
+/// interpreter words its panics differently.
+pub fn agrees(a: &Outcome, b: &Outcome) -> bool {
+    match (a, b) {
+        (Outcome::Crashed(_), Outcome::Crashed(_)) => true,
+        _ => a == b,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    pub program: Program,
+    pub input: i64,
+    pub outcomes: Vec<(&'static str, Outcome)>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "program: {}", self.program)?;
+        writeln!(f, "input: {}", self.input)?;
+        for (name, outcome) in self.outcomes.iter() {
+            writeln!(f, "  {:>12}: {:?}", name, outcome)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Harness {
+    backends: Vec<Box<dyn Backend>>,
+    options: FuzzOptions,
+}
+
+impl Harness {
+    pub fn new(backends: Vec<Box<dyn Backend>>, options: FuzzOptions) -> Self {
+        let backends = backends
+            .into_iter()
+            .filter(|backend| backend.supports(options.profile))
+            .collect();
+        Harness { backends, options }
+    }
+
+    pub fn outcomes(&self, program: &Program, input: i64) -> Vec<(&'static str, Outcome)> {
+        let memory = program.encode();
+        self.backends
+            .iter()
+            .map(|backend| {
+                let outcome = backend.run(&memory, input, self.options.max_steps);
+                (backend.name(), outcome)
+            })
+            .collect()
+    }
+
+    /// Runs `program` on every backend and returns the outcomes if any two
+    /// of them disagree.
+    pub fn check(&self, program: &Program, input: i64) -> Option<Divergence> {
+        let outcomes = self.outcomes(program, input);
+        let diverged = outcomes
+            .iter()
+            .skip(1)
+            .any(|(_, outcome)| !agrees(&outcomes[0].1, outcome));
+        if diverged {
+            Some(Divergence {
+                program: program.clone(),
+                input,
+                outcomes,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Greedily applies shrink steps while the same pair of backends keeps
+    /// disagreeing, so the result still shows the original bug rather than
+    /// some other one uncovered along the way.
+    pub fn minimize(&self, divergence: &Divergence) -> Divergence {
+        let pair = diverging_pair(&divergence.outcomes);
+        let still_fails = |candidate: &Divergence| diverging_pair(&candidate.outcomes) == pair;
+
+        let mut best = divergence.clone();
+        loop {
+            let mut inputs = smaller_values(best.input)
+                .into_iter()
+                .map(|input| (best.program.clone(), input))
+                .collect::<Vec<_>>();
+            inputs.extend(
+                best.program
+                    .shrink_candidates()
+                    .into_iter()
+                    .map(|program| (program, best.input)),
+            );
+
+            let next = inputs.into_iter().find_map(|(program, input)| {
+                self.check(&program, input)
+                    .filter(|candidate| still_fails(candidate))
+            });
+            match next {
+                Some(smaller) => best = smaller,
+                None => return best,
+            }
+        }
+    }
+
+    /// Generates `iterations` programs from `seed` and returns every
+    /// divergence found, already minimised.
+    pub fn fuzz(&self, seed: u64, iterations: usize) -> Vec<Divergence> {
+        let mut rng = Rng::new(seed);
+        let mut found = Vec::new();
+        for _ in 0..iterations {
+            let program = Program::generate(&mut rng, &self.options);
+            let input = match self.options.profile {
+                Profile::Day2 => 0,
+                Profile::Day5 => rng.range(-self.options.max_value, self.options.max_value),
+            };
+            if let Some(divergence) = self.check(&program, input) {
+                found.push(self.minimize(&divergence));
+            }
+        }
+        found
+    }
+}
+
+/// The first two backends that disagree, by name.
+fn diverging_pair(outcomes: &[(&'static str, Outcome)]) -> Option<(&'static str, &'static str)> {
+    for (i, (name_a, a)) in outcomes.iter().enumerate() {
+        for (name_b, b) in outcomes[i + 1..].iter() {
+            if !agrees(a, b) {
+                return Some((name_a, name_b));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::all_backends;
+
+    #[test]
+    fn test_encode() {
+        let program = Program {
+            ops: vec![
+                Op::Input(0),
+                Op::Mult(Param::Data(0), Param::Immediate(3), 1),
+                Op::JumpIfTrue(Param::Immediate(1), 4),
+                Op::Output(Param::Data(1)),
+            ],
+            data: vec![0, 0],
+        };
+        assert_eq!(
+            program.encode(),
+            vec![3, 12, 1002, 12, 3, 13, 1105, 1, 11, 4, 13, 99, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_without_op_retargets_jumps() {
+        let program = Program {
+            ops: vec![
+                Op::Input(0),
+                Op::Output(Param::Data(0)),
+                Op::JumpIfFalse(Param::Data(0), 3),
+            ],
+            data: vec![0],
+        };
+        let smaller = program.without_op(0);
+        assert_eq!(
+            smaller.ops,
+            vec![
+                Op::Output(Param::Data(0)),
+                Op::JumpIfFalse(Param::Data(0), 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_backends_agree_on_small_programs() {
+        let mut options = FuzzOptions::new(Profile::Day2);
+        options.max_value = 9;
+        options.max_ops = 3;
+        let harness = Harness::new(all_backends(), options);
+        assert!(harness.fuzz(1, 200).is_empty());
+    }
+
+    #[cfg(feature = "fuzz")]
+    #[test]
+    fn test_finds_and_minimizes_i32_overflow() {
+        let program = Program {
+            ops: vec![
+                Op::Input(1),
+                Op::Output(Param::Data(1)),
+                Op::Mult(Param::Data(0), Param::Data(0), 0),
+                Op::Add(Param::Immediate(7), Param::Data(1), 1),
+            ],
+            data: vec![100_000, 0],
+        };
+        let harness = Harness::new(all_backends(), FuzzOptions::new(Profile::Day5));
+        let divergence = harness.check(&program, 5).expect("i32 should overflow");
+        assert_eq!(
+            diverging_pair(&divergence.outcomes),
+            Some(("day-05 i32", "intcode i64"))
+        );
+
+        let minimal = harness.minimize(&divergence);
+        assert_eq!(
+            minimal.program.ops,
+            vec![Op::Mult(Param::Data(0), Param::Data(0), 0)]
+        );
+        assert_eq!(minimal.input, 0);
+    }
+}
@@ -0,0 +1,12 @@
+pub mod async_computer;
+pub mod backends;
+pub mod cases;
+pub mod computer;
+pub mod decoded;
+pub mod device;
+pub mod ffi;
+pub mod fuzz;
+pub mod server;
+
+pub use async_computer::{AsyncComputer, IntcodeComputer};
+pub use computer::{load_program, parse_field, parse_fields, Computer, Memory};
@@ -0,0 +1,316 @@
+//! A line protocol for driving a computer from outside Rust. Each request is
+//! one line and gets exactly one line back, starting with `ok` or `error`.
+//!
+//! ```text
+//! input 1 2 3        queue input values
+//! output             drain queued output        -> ok 4 5 6
+//! step [n]           step n instructions, or    -> ok running pc=12
+//!                    until halted/blocked
+//! back [n]           undo n instructions        -> ok running pc=9
+//! run [n]            run until halted/blocked   -> ok blocked pc=40
+//!                    or n steps have run           (or running pc=.. budget=n)
+//! status                                        -> ok halted pc=57
+//!                                                  (or failed pc=.. <error>)
+//! snapshot [name]    save the current state
+//! restore [name]     go back to a saved state
+//! reset              reload the program
+//! quit               close the session
+//! ```
+
+use crate::computer::{Computer, Memory};
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+const DEFAULT_SNAPSHOT: &str = "default";
+const HISTORY_LIMIT: usize = 10_000;
+/// How many steps `run` takes when the request doesn't say, and the most
+/// `step` or `run` will take for one request, so a program that never stops
+/// can't tie up the server.
+const RUN_BUDGET: usize = 1_000_000;
+
+pub enum Reply {
+    Line(String),
+    Quit,
+}
+
+/// One client's computer. Sessions share nothing, so any number of them can
+/// run the same program side by side.
+pub struct Session {
+    program: Memory,
+    cpu: Computer,
+    snapshots: HashMap<String, Computer>,
+}
+
+impl Session {
+    pub fn new(program: &Memory) -> Self {
+        Session {
+            program: program.clone(),
+            cpu: Session::load(program),
+            snapshots: HashMap::new(),
+        }
+    }
+
+    fn load(program: &Memory) -> Computer {
+        let mut cpu = Computer::new(program);
+        cpu.enable_history(HISTORY_LIMIT);
+        cpu
+    }
+
+    pub fn status(&self) -> String {
+        let state = if let Some(error) = self.cpu.get_error() {
+            return format!("failed pc={} {}", self.cpu.get_pc(), error);
+        } else if self.cpu.is_halted() {
+            "halted"
+        } else if self.cpu.is_blocked() {
+            "blocked"
+        } else {
+            "running"
+        };
+        format!("{} pc={}", state, self.cpu.get_pc())
+    }
+
+    pub fn handle(&mut self, line: &str) -> Reply {
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => return Reply::Line("error empty request".to_string()),
+        };
+        let args = words.collect::<Vec<_>>();
+
+        let result = match command {
+            "input" => self.input(&args),
+            "output" => Ok(self.output()),
+            "step" => self.step(&args),
+            "back" => self.back(&args),
+            "run" => self.run(&args),
+            "status" => Ok(self.status()),
+            "snapshot" => {
+                let name = args.first().unwrap_or(&DEFAULT_SNAPSHOT);
+                self.snapshots.insert(name.to_string(), self.cpu.clone());
+                Ok(String::new())
+            }
+            "restore" => {
+                let name = args.first().unwrap_or(&DEFAULT_SNAPSHOT);
+                match self.snapshots.get(*name) {
+                    Some(cpu) => {
+                        self.cpu = cpu.clone();
+                        Ok(self.status())
+                    }
+                    None => Err(format!("no snapshot named {}", name)),
+                }
+            }
+            "reset" => {
+                self.cpu = Session::load(&self.program);
+                Ok(self.status())
+            }
+            "quit" => return Reply::Quit,
+            other => Err(format!("unknown command {}", other)),
+        };
+
+        Reply::Line(match result {
+            Ok(text) if text.is_empty() => "ok".to_string(),
+            Ok(text) => format!("ok {}", text),
+            Err(message) => format!("error {}", message),
+        })
+    }
+
+    fn input(&mut self, args: &[&str]) -> Result<String, String> {
+        let values = args
+            .iter()
+            .map(|arg| arg.parse::<i64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| "input values must be integers".to_string())?;
+        for data in values {
+            self.cpu.set_input(data);
+        }
+        Ok(String::new())
+    }
+
+    fn output(&mut self) -> String {
+        let mut values = Vec::new();
+        while let Some(data) = self.cpu.get_output() {
+            values.push(data.to_string());
+        }
+        values.join(" ")
+    }
+
+    fn step(&mut self, args: &[&str]) -> Result<String, String> {
+        self.advance(step_count(args, 1)?.min(RUN_BUDGET));
+        Ok(self.status())
+    }
+
+    fn run(&mut self, args: &[&str]) -> Result<String, String> {
+        let budget = step_count(args, RUN_BUDGET)?.min(RUN_BUDGET);
+        if self.advance(budget) {
+            Ok(format!("{} budget={}", self.status(), budget))
+        } else {
+            Ok(self.status())
+        }
+    }
+
+    /// Steps until the computer halts, blocks or fails, or `limit` steps
+    /// have run. Returns true if it could still have gone on.
+    fn advance(&mut self, limit: usize) -> bool {
+        for _ in 0..limit {
+            if self.is_stopped() {
+                return false;
+            }
+            self.cpu.step();
+        }
+        !self.is_stopped()
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.cpu.is_halted() || self.cpu.is_blocked() || self.cpu.get_error().is_some()
+    }
+
+    fn back(&mut self, args: &[&str]) -> Result<String, String> {
+        let n = step_count(args, 1)?;
+        let available = self.cpu.history_len();
+        if available < n {
+            return Err(format!("only {} steps of history", available));
+        }
+        self.cpu.step_back(n);
+        Ok(self.status())
+    }
+}
+
+fn step_count(args: &[&str], default: usize) -> Result<usize, String> {
+    match args.first() {
+        Some(arg) => arg
+            .parse::<usize>()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| "step count must be a positive integer".to_string()),
+        None => Ok(default),
+    }
+}
+
+/// Answers requests from `reader` until the client quits or hangs up.
+pub fn serve<R, W>(session: &mut Session, reader: R, mut writer: W) -> io::Result<()>
+where
+    R: BufRead,
+    W: Write,
+{
+    for line in reader.lines() {
+        match session.handle(&line?) {
+            Reply::Line(reply) => {
+                writeln!(writer, "{}", reply)?;
+                writer.flush()?;
+            }
+            Reply::Quit => break,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Outputs the sum of two inputs, then halts.
+    fn adder() -> Memory {
+        vec![3, 11, 3, 12, 1, 11, 12, 13, 4, 13, 99, 0, 0, 0]
+    }
+
+    fn reply(session: &mut Session, line: &str) -> String {
+        match session.handle(line) {
+            Reply::Line(reply) => reply,
+            Reply::Quit => "quit".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_run_and_output() {
+        let mut session = Session::new(&adder());
+        assert_eq!(reply(&mut session, "run"), "ok blocked pc=0");
+        assert_eq!(reply(&mut session, "input 2 3"), "ok");
+        assert_eq!(reply(&mut session, "run"), "ok halted pc=11");
+        assert_eq!(reply(&mut session, "output"), "ok 5");
+        assert_eq!(reply(&mut session, "output"), "ok");
+    }
+
+    #[test]
+    fn test_run_budget() {
+        // Jumps back and forth forever.
+        let mut session = Session::new(&vec![1105, 1, 3, 1105, 1, 0]);
+        assert_eq!(reply(&mut session, "run 5"), "ok running pc=3 budget=5");
+        assert_eq!(reply(&mut session, "run 5"), "ok running pc=0 budget=5");
+        assert_eq!(
+            reply(&mut session, "run"),
+            format!("ok running pc=0 budget={}", RUN_BUDGET)
+        );
+    }
+
+    #[test]
+    fn test_step_stops() {
+        let mut session = Session::new(&adder());
+        assert_eq!(reply(&mut session, "step 5"), "ok blocked pc=0");
+        assert_eq!(reply(&mut session, "back"), "ok running pc=0");
+        assert_eq!(reply(&mut session, "input 2 3"), "ok");
+        assert_eq!(reply(&mut session, "step 100"), "ok halted pc=11");
+        assert_eq!(reply(&mut session, "back 2"), "ok running pc=8");
+
+        let mut session = Session::new(&vec![42]);
+        assert_eq!(
+            reply(&mut session, "step 3"),
+            "ok failed pc=0 unknown op code 42 at pc 0"
+        );
+    }
+
+    #[test]
+    fn test_step_snapshot_restore_reset() {
+        let mut session = Session::new(&adder());
+        reply(&mut session, "input 2 3");
+        assert_eq!(reply(&mut session, "step"), "ok running pc=2");
+        assert_eq!(reply(&mut session, "snapshot"), "ok");
+        assert_eq!(reply(&mut session, "step 3"), "ok running pc=10");
+        assert_eq!(reply(&mut session, "restore"), "ok running pc=2");
+        assert_eq!(reply(&mut session, "back"), "ok running pc=0");
+        assert_eq!(reply(&mut session, "step"), "ok running pc=2");
+        assert_eq!(reply(&mut session, "run"), "ok halted pc=11");
+        assert_eq!(reply(&mut session, "reset"), "ok running pc=0");
+        assert_eq!(reply(&mut session, "output"), "ok");
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut session = Session::new(&adder());
+        assert_eq!(
+            reply(&mut session, "input x"),
+            "error input values must be integers"
+        );
+        assert_eq!(
+            reply(&mut session, "restore old"),
+            "error no snapshot named old"
+        );
+        assert_eq!(
+            reply(&mut session, "back 2"),
+            "error only 0 steps of history"
+        );
+        assert_eq!(
+            reply(&mut session, "step 0"),
+            "error step count must be a positive integer"
+        );
+        assert_eq!(
+            reply(&mut session, "run 0"),
+            "error step count must be a positive integer"
+        );
+        assert_eq!(reply(&mut session, "fly"), "error unknown command fly");
+        assert_eq!(reply(&mut session, ""), "error empty request");
+        assert_eq!(reply(&mut session, "quit"), "quit");
+    }
+
+    #[test]
+    fn test_serve() {
+        let mut session = Session::new(&adder());
+        let requests = "input 4 5\nrun\noutput\nquit\nstatus\n";
+        let mut replies = Vec::new();
+        serve(&mut session, requests.as_bytes(), &mut replies).unwrap();
+        assert_eq!(
+            String::from_utf8(replies).unwrap(),
+            "ok\nok halted pc=11\nok 9\n"
+        );
+    }
+}
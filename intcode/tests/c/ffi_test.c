@@ -0,0 +1,68 @@
+/* Exercises the C API end to end. Exits non-zero on the first failure. */
+
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "intcode.h"
+
+#define CHECK(cond)                                                        \
+    do {                                                                   \
+        if (!(cond)) {                                                     \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__,         \
+                    __LINE__, #cond);                                      \
+            exit(1);                                                       \
+        }                                                                  \
+    } while (0)
+
+/* Reads a value, multiplies it by three and outputs it. */
+static const int64_t TRIPLER[] = {3, 9, 1002, 9, 3, 9, 4, 9, 99, 0};
+
+/* Starts with an op code the interpreter does not know. */
+static const int64_t BAD_OP_CODE[] = {42, 0, 0, 0};
+
+int main(void) {
+    int64_t data = 0;
+    intcode_vm *vm = intcode_new(TRIPLER, sizeof(TRIPLER) / sizeof(TRIPLER[0]));
+    CHECK(vm != NULL);
+
+    CHECK(intcode_status(vm) == INTCODE_RUNNING);
+    CHECK(intcode_step(vm) == INTCODE_BLOCKED);
+    CHECK(intcode_pc(vm) == 0);
+    CHECK(intcode_run(vm) == INTCODE_BLOCKED);
+
+    intcode_snapshot *snapshot = intcode_take_snapshot(vm);
+    CHECK(snapshot != NULL);
+
+    CHECK(intcode_push_input(vm, 14) == INTCODE_RUNNING);
+    CHECK(intcode_run(vm) == INTCODE_OUTPUT);
+    CHECK(intcode_pop_output(vm, &data) == 1);
+    CHECK(data == 42);
+    CHECK(intcode_pop_output(vm, &data) == 0);
+    CHECK(intcode_run(vm) == INTCODE_HALTED);
+
+    CHECK(intcode_restore(vm, snapshot) == INTCODE_BLOCKED);
+    CHECK(intcode_read_memory(vm, 4, &data) == INTCODE_RUNNING);
+    CHECK(data == 3);
+    CHECK(intcode_write_memory(vm, 4, 7) == INTCODE_RUNNING);
+    CHECK(intcode_write_memory(vm, (size_t)-1, 7) == INTCODE_BAD_ARGUMENT);
+    intcode_push_input(vm, 6);
+    CHECK(intcode_run(vm) == INTCODE_OUTPUT);
+    CHECK(intcode_pop_output(vm, &data) == 1);
+    CHECK(data == 42);
+
+    intcode_snapshot_free(snapshot);
+    intcode_free(vm);
+
+    vm = intcode_new(BAD_OP_CODE, sizeof(BAD_OP_CODE) / sizeof(BAD_OP_CODE[0]));
+    CHECK(vm != NULL);
+    CHECK(intcode_run(vm) == INTCODE_ERROR);
+    CHECK(intcode_step(vm) == INTCODE_ERROR);
+    CHECK(intcode_status(vm) == INTCODE_ERROR);
+    intcode_free(vm);
+
+    CHECK(intcode_run(NULL) == INTCODE_BAD_ARGUMENT);
+    CHECK(intcode_new(NULL, 0) == NULL);
+
+    printf("ok\n");
+    return 0;
+}
@@ -0,0 +1,46 @@
+//! Builds `tests/c/ffi_test.c` against the cdylib with the system C compiler
+//! and runs it.
+
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+#[test]
+fn test_c_api() {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let deps_dir = env::current_exe()
+        .unwrap()
+        .parent()
+        .expect("Test binary has no parent directory")
+        .to_path_buf();
+    assert!(
+        deps_dir.join("libintcode.so").exists(),
+        "libintcode.so was not built next to the test binary"
+    );
+
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let exe = deps_dir.join("intcode_ffi_test");
+    let status = Command::new(&compiler)
+        .arg(manifest_dir.join("tests/c/ffi_test.c"))
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg("-L")
+        .arg(&deps_dir)
+        .arg("-lintcode")
+        .arg("-o")
+        .arg(&exe)
+        .status()
+        .unwrap_or_else(|err| panic!("Failed to run C compiler {}: {}", compiler, err));
+    assert!(status.success(), "C test program failed to compile");
+
+    let output = Command::new(&exe)
+        .env("LD_LIBRARY_PATH", &deps_dir)
+        .output()
+        .expect("Failed to run C test program");
+    assert!(
+        output.status.success(),
+        "C test program failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+}